@@ -0,0 +1,115 @@
+use super::{Buffer, Position};
+use crate::text::{Range, TextBuffer};
+use crate::text_object::TextObject;
+
+/**
+ * Linewise editing. Line text is always returned and inserted with a
+ * trailing newline per line.
+ */
+impl<T: TextBuffer> Buffer<T> {
+    /**
+     * The text of lines start to end, inclusive
+     */
+    pub fn lines(&self, start: usize, end: usize) -> String {
+        (start..=end)
+            .map(|line| self.text_buffer.line(line) + "\n")
+            .collect()
+    }
+
+    /**
+     * Delete lines start to end, inclusive, and return their text
+     */
+    pub fn delete_lines(&mut self, start: usize, end: usize) -> String {
+        let removed = self.lines(start, end);
+        let end_col = self.text_buffer.line_length(end);
+        let has_newline = self
+            .text_buffer
+            .chars(Position::new(end, end_col))
+            .next()
+            .is_some();
+
+        // Without a newline after the last line, take the one before the first
+        let range = if has_newline {
+            TextObject::linewise(start, end).range(self.text_buffer.as_ref())
+        } else if start > 0 {
+            let prev_col = self.text_buffer.line_length(start - 1);
+            Range::new(Position::new(start - 1, prev_col), removed.len())
+        } else {
+            Range::new(Position::new(0, 0), removed.len() - 1)
+        };
+        if range.length > 0 {
+            self.text_buffer.delete(range);
+        }
+
+        let last = self.text_buffer.line_count().max(1) - 1;
+        self.cursor = Position::new(start.min(last), 0);
+        removed
+    }
+
+    /**
+     * Insert lines below the 1-indexed line `after`. 0 inserts at the top.
+     */
+    pub fn insert_lines(&mut self, after: usize, text: &str) {
+        if after == 0 {
+            self.text_buffer.insert_str(Position::new(0, 0), text);
+            return;
+        }
+
+        let line = after - 1;
+        let end_col = self.text_buffer.line_length(line);
+        let has_newline = self
+            .text_buffer
+            .chars(Position::new(line, end_col))
+            .next()
+            .is_some();
+        if has_newline {
+            self.text_buffer.insert_str(Position::new(after, 0), text);
+        } else {
+            let text = format!("\n{}", text.strip_suffix('\n').unwrap_or(text));
+            self.text_buffer
+                .insert_str(Position::new(line, end_col), text.as_str());
+        }
+    }
+
+    /**
+     * Replace lines start to end, inclusive, with new text
+     */
+    pub fn replace_lines(&mut self, start: usize, end: usize, text: &str) {
+        self.delete_lines(start, end);
+        self.insert_lines(start, text);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::text::PieceTableBuffer;
+
+    fn buffer(text: &str) -> Buffer<PieceTableBuffer> {
+        Buffer::new(Box::new(PieceTableBuffer::new(text.to_string())))
+    }
+
+    #[test]
+    fn test_delete_lines() {
+        let mut buf = buffer("one\ntwo\nthree\n");
+        assert_eq!(buf.delete_lines(1, 2), "two\nthree\n");
+        assert_eq!(buf.text_buffer.to_string(), "one\n");
+
+        // No trailing newline
+        let mut buf = buffer("one\ntwo");
+        assert_eq!(buf.delete_lines(1, 1), "two\n");
+        assert_eq!(buf.text_buffer.to_string(), "one");
+    }
+
+    #[test]
+    fn test_insert_lines() {
+        let mut buf = buffer("one\ntwo\n");
+        buf.insert_lines(0, "zero\n");
+        buf.insert_lines(3, "three\n");
+        assert_eq!(buf.text_buffer.to_string(), "zero\none\ntwo\nthree\n");
+
+        let mut buf = buffer("one");
+        buf.insert_lines(1, "two\n");
+        assert_eq!(buf.text_buffer.to_string(), "one\ntwo");
+    }
+}
@@ -0,0 +1,33 @@
+use super::Position;
+use std::collections::HashMap;
+
+/**
+ * Named positions in a buffer, set with m{a-z} and used by ' and ` as
+ * well as ex ranges.
+ */
+#[derive(Debug, Clone)]
+pub struct Marks {
+    named: HashMap<char, Position>,
+}
+
+impl Marks {
+    pub fn new() -> Self {
+        Marks {
+            named: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, mark: char) -> Option<Position> {
+        self.named.get(&mark).copied()
+    }
+
+    pub fn set(&mut self, mark: char, pos: Position) {
+        self.named.insert(mark, pos);
+    }
+}
+
+impl Default for Marks {
+    fn default() -> Self {
+        Marks::new()
+    }
+}
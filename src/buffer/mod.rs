@@ -1,8 +1,15 @@
+mod lines;
+mod marks;
 pub mod motions;
 
-use crate::text::TextBuffer;
+pub use marks::Marks;
 
-#[derive(Debug, Clone, Copy)]
+use crate::options::BufferOptions;
+use crate::syntax::Highlighter;
+use crate::text::{PieceTableBuffer, Range, TextBuffer};
+use crate::text_object::TextObject;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Position {
     pub line: usize,
     pub col: usize,
@@ -14,10 +21,39 @@ impl Position {
     }
 }
 
+/**
+ * The screen column after a character drawn at `col`. Tabs reach the next
+ * multiple of the tabstop.
+ */
+pub fn next_col(col: usize, c: char, tabstop: usize) -> usize {
+    if c == '\t' {
+        (col / tabstop + 1) * tabstop
+    } else {
+        col + 1
+    }
+}
+
+/**
+ * The chars that make up the first length bytes
+ */
+fn take_bytes<I: Iterator<Item = char>>(chars: I, length: usize) -> String {
+    let mut text = String::new();
+    for c in chars {
+        if text.len() >= length {
+            break;
+        }
+        text.push(c);
+    }
+    text
+}
+
 #[derive(Debug, Clone)]
 pub struct Buffer<T: TextBuffer> {
     pub cursor: Position,
     pub text_buffer: Box<T>,
+    pub marks: Marks,
+    pub syntax: Option<Highlighter>,
+    pub options: BufferOptions,
 }
 
 impl<T: TextBuffer> Buffer<T> {
@@ -25,6 +61,9 @@ impl<T: TextBuffer> Buffer<T> {
         Buffer {
             cursor: Position::new(0, 0),
             text_buffer,
+            marks: Marks::new(),
+            syntax: None,
+            options: BufferOptions::new(),
         }
     }
 
@@ -42,21 +81,45 @@ impl<T: TextBuffer> Buffer<T> {
         }
     }
 
+    /**
+     * Insert text at the cursor, leaving the cursor after it
+     */
+    pub fn insert_str(&mut self, text: &str) {
+        let cursor = self.cursor();
+        self.text_buffer.insert_str(cursor, text);
+
+        self.cursor = match text.rfind('\n') {
+            Some(idx) => Position::new(
+                cursor.line + text.matches('\n').count(),
+                text[idx + 1..].len(),
+            ),
+            None => Position::new(cursor.line, cursor.col + text.len()),
+        };
+    }
+
     pub fn delete(&mut self) {
         let cursor = self.cursor();
         if cursor.line == 0 && cursor.col == 0 {
             return;
         }
 
-        // Update the cursor to the previous position
-        if cursor.col > 0 {
-            self.cursor.col -= 1
+        // Update the cursor to the previous position, a whole char back
+        let length = if cursor.col > 0 {
+            let line = self.text_buffer.line(cursor.line);
+            let (col, c) = line
+                .char_indices()
+                .take_while(|&(idx, _)| idx < cursor.col)
+                .last()
+                .unwrap_or((0, ' '));
+            self.cursor.col = col;
+            c.len_utf8()
         } else {
             self.cursor.line -= 1;
             self.cursor.col = self.text_buffer.line_length(self.cursor.line);
-        }
+            1
+        };
 
-        self.text_buffer.delete(self.cursor)
+        self.text_buffer.delete(Range::new(self.cursor, length))
     }
 
     pub fn move_cursor(&mut self, pos: Position) {
@@ -75,6 +138,34 @@ impl<T: TextBuffer> Buffer<T> {
         }
     }
 
+    /**
+     * The screen column of a position, counting from the start of its line
+     */
+    pub fn display_col(&self, pos: Position) -> usize {
+        let tabstop = self.options.tabstop;
+        self.text_buffer
+            .line(pos.line)
+            .char_indices()
+            .take_while(|&(idx, _)| idx < pos.col)
+            .fold(0, |col, (_, c)| next_col(col, c, tabstop))
+    }
+
+    /**
+     * The byte column of the character shown at a screen column of a line, or
+     * the end of the line if it's shorter
+     */
+    pub fn col_at_display(&self, line: usize, display_col: usize) -> usize {
+        let tabstop = self.options.tabstop;
+        let mut screen_col = 0;
+        for (col, c) in self.text_buffer.line(line).char_indices() {
+            screen_col = next_col(screen_col, c, tabstop);
+            if screen_col > display_col {
+                return col;
+            }
+        }
+        self.text_buffer.line_length(line)
+    }
+
     pub fn line_above(&mut self) {
         let line = self.cursor.line;
         self.text_buffer.insert(Position::new(line, 0), '\n');
@@ -87,4 +178,99 @@ impl<T: TextBuffer> Buffer<T> {
         self.text_buffer.insert(Position::new(line, col), '\n');
         self.cursor = Position::new(line + 1, 0);
     }
+
+    /**
+     * The text from start to end, inclusive
+     */
+    pub fn text_between(&self, start: Position, end: Position) -> String {
+        let range = TextObject::charwise(start, end).range(self.text_buffer.as_ref());
+        take_bytes(self.text_buffer.chars(start), range.length)
+    }
+
+    /**
+     * Delete the text from start to end, inclusive, and return it
+     */
+    pub fn delete_between(&mut self, start: Position, end: Position) -> String {
+        let range = TextObject::charwise(start, end).range(self.text_buffer.as_ref());
+        let removed = take_bytes(self.text_buffer.chars(start), range.length);
+        if range.length > 0 {
+            self.text_buffer.delete(range);
+        }
+        self.cursor = start;
+        removed
+    }
+}
+
+impl Buffer<PieceTableBuffer> {
+    /**
+     * Bring syntax highlighting up to date through the given line
+     */
+    pub fn update_syntax(&mut self, last: usize) {
+        if let Some(syntax) = &mut self.syntax {
+            syntax.update(&mut self.text_buffer, last);
+        }
+    }
+
+    /**
+     * The function around the cursor, from the syntax tree
+     */
+    pub fn function_object(&mut self) -> Option<(Position, Position)> {
+        self.update_syntax(0);
+        self.syntax.as_ref()?.function_around(self.cursor())
+    }
+
+    /**
+     * The syntax node around an inclusive range
+     */
+    pub fn expand_selection(
+        &mut self,
+        start: Position,
+        end: Position,
+    ) -> Option<(Position, Position)> {
+        self.update_syntax(0);
+        self.syntax.as_ref()?.expand(start, end)
+    }
+
+    /**
+     * The start of the next (or previous) function, from the syntax tree
+     */
+    pub fn next_function(&mut self, forward: bool) -> Option<Position> {
+        self.update_syntax(0);
+        self.syntax.as_ref()?.next_function(self.cursor(), forward)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::text::PieceTableBuffer;
+
+    #[test]
+    fn test_display_col() {
+        let text = "\té\tx\n".to_string();
+        let buffer = Buffer::new(Box::new(PieceTableBuffer::new(text)));
+        assert_eq!(buffer.display_col(Position::new(0, 1)), 8);
+        assert_eq!(buffer.display_col(Position::new(0, 3)), 9);
+        assert_eq!(buffer.display_col(Position::new(0, 4)), 16);
+        assert_eq!(buffer.col_at_display(0, 8), 1);
+        assert_eq!(buffer.col_at_display(0, 12), 3);
+        assert_eq!(buffer.col_at_display(0, 20), 5);
+    }
+
+    #[test]
+    fn test_display_col_mid_char() {
+        let text = "ab\néa\n".to_string();
+        let buffer = Buffer::new(Box::new(PieceTableBuffer::new(text)));
+        assert_eq!(buffer.display_col(Position::new(1, 1)), 1);
+    }
+
+    #[test]
+    fn test_delete_mid_char() {
+        let text = "éa\n".to_string();
+        let mut buffer = Buffer::new(Box::new(PieceTableBuffer::new(text)));
+        buffer.move_cursor(Position::new(0, 1));
+        buffer.delete();
+        assert_eq!(buffer.cursor(), Position::new(0, 0));
+        assert_eq!(buffer.text_buffer.line(0), "a");
+    }
 }
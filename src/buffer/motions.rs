@@ -47,6 +47,12 @@ impl<T: TextBuffer> Buffer<T> {
         Position::new(self.cursor.line, 0)
     }
 
+    pub fn first_non_blank(&self) -> Position {
+        let line = self.text_buffer.line(self.cursor.line);
+        let col = line.len() - line.trim_start().len();
+        Position::new(self.cursor.line, col)
+    }
+
     pub fn end_line(&self) -> Position {
         Position::new(
             self.cursor.line,
@@ -0,0 +1,401 @@
+use crate::buffer::Buffer;
+use crate::editor::Editor;
+use crate::ex::ExError;
+use crate::file::{load_file, FileInfo};
+use crate::text::{PieceTableBuffer, TextBuffer};
+use std::error::Error;
+use std::mem;
+
+/**
+ * A buffer that isn't the current one, with everything needed to switch
+ * back to it
+ */
+pub struct BufferEntry {
+    pub id: usize,
+    pub buffer: Buffer<PieceTableBuffer>,
+    pub filename: Option<String>,
+    pub file_info: FileInfo,
+    pub offset: usize,
+}
+
+impl BufferEntry {
+    fn new(
+        id: usize,
+        buffer: Buffer<PieceTableBuffer>,
+        filename: Option<String>,
+        file_info: FileInfo,
+    ) -> Self {
+        BufferEntry {
+            id,
+            buffer,
+            filename,
+            file_info,
+            offset: 0,
+        }
+    }
+
+    fn empty(id: usize) -> Self {
+        let buffer = Buffer::new(Box::new(PieceTableBuffer::new("".to_string())));
+        BufferEntry::new(id, buffer, None, FileInfo::default())
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.buffer.text_buffer.change_tick != self.file_info.saved_tick
+            || self.file_info.format != self.file_info.saved_format
+    }
+}
+
+/**
+ * Every open buffer. The current buffer lives in the editor's own fields,
+ * so only its id is kept here, and the others are swapped in as needed.
+ */
+pub struct BufferList {
+    pub current: usize,
+    pub alternate: Option<usize>,
+    // Sorted by id
+    hidden: Vec<BufferEntry>,
+    next_id: usize,
+}
+
+impl BufferList {
+    pub fn new() -> Self {
+        BufferList {
+            current: 1,
+            alternate: None,
+            hidden: vec![],
+            next_id: 2,
+        }
+    }
+
+    pub fn hidden(&self) -> &[BufferEntry] {
+        &self.hidden
+    }
+
+    /**
+     * All buffer ids in order, including the current one
+     */
+    pub fn ids(&self) -> Vec<usize> {
+        let mut ids = self
+            .hidden
+            .iter()
+            .map(|entry| entry.id)
+            .collect::<Vec<usize>>();
+        ids.push(self.current);
+        ids.sort_unstable();
+        ids
+    }
+
+    pub fn get(&self, id: usize) -> Option<&BufferEntry> {
+        self.hidden.iter().find(|entry| entry.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut BufferEntry> {
+        self.hidden.iter_mut().find(|entry| entry.id == id)
+    }
+
+    fn find_file(&self, filename: &str) -> Option<usize> {
+        self.hidden
+            .iter()
+            .find(|entry| entry.filename.as_deref() == Some(filename))
+            .map(|entry| entry.id)
+    }
+
+    fn take(&mut self, id: usize) -> Option<BufferEntry> {
+        let idx = self.hidden.iter().position(|entry| entry.id == id)?;
+        Some(self.hidden.remove(idx))
+    }
+
+    fn insert(&mut self, entry: BufferEntry) {
+        let idx = self
+            .hidden
+            .iter()
+            .position(|other| other.id > entry.id)
+            .unwrap_or(self.hidden.len());
+        self.hidden.insert(idx, entry);
+    }
+
+    fn next_id(&mut self) -> usize {
+        self.next_id += 1;
+        self.next_id - 1
+    }
+}
+
+impl Default for BufferList {
+    fn default() -> Self {
+        BufferList::new()
+    }
+}
+
+impl Editor {
+    /**
+     * Open a file in a new buffer, or switch to it if it's already open.
+     * An empty, unnamed current buffer is reused.
+     */
+    pub fn open_file(&mut self, filename: &str) -> Result<(), Box<dyn Error + 'static>> {
+        if let Some(id) = self.buffers.find_file(filename) {
+            self.switch_buffer(id)?;
+            return Ok(());
+        }
+
+        let (mut buffer, info) = load_file(filename)?;
+        buffer.options = self.options.buffer.clone();
+        self.messages
+            .info(file_message(filename, &buffer, &info).as_str());
+
+        let filename = Some(filename.to_string());
+        let reuse = self.filename == filename || self.is_scratch();
+        let id = if reuse {
+            self.buffers.current
+        } else {
+            self.buffers.next_id()
+        };
+        let old = self.swap_current(BufferEntry::new(id, buffer, filename, info));
+        if !reuse {
+            self.buffers.alternate = Some(old.id);
+            self.buffers.insert(old);
+        }
+        Ok(())
+    }
+
+    /**
+     * Add a file to the buffer list without making it current
+     */
+    pub fn add_file(&mut self, filename: &str) -> Result<(), Box<dyn Error + 'static>> {
+        if self.buffers.find_file(filename).is_some() || self.filename.as_deref() == Some(filename)
+        {
+            return Ok(());
+        }
+        let (mut buffer, info) = load_file(filename)?;
+        buffer.options = self.options.buffer.clone();
+        let id = self.buffers.next_id();
+        let entry = BufferEntry::new(id, buffer, Some(filename.to_string()), info);
+        self.buffers.insert(entry);
+        Ok(())
+    }
+
+    /**
+     * Switch to a new empty buffer, unless the current one already is
+     */
+    pub fn new_buffer(&mut self) {
+        if self.is_scratch() {
+            return;
+        }
+        let mut entry = BufferEntry::empty(self.buffers.next_id());
+        entry.buffer.options = self.options.buffer.clone();
+        let old = self.swap_current(entry);
+        self.buffers.alternate = Some(old.id);
+        self.buffers.insert(old);
+    }
+
+    pub fn switch_buffer(&mut self, id: usize) -> Result<(), ExError> {
+        if id == self.buffers.current {
+            return Ok(());
+        }
+        let entry = self.buffers.take(id).ok_or(ExError::NoSuchBuffer(id))?;
+        let old = self.swap_current(entry);
+        self.buffers.alternate = Some(old.id);
+        self.buffers.insert(old);
+        Ok(())
+    }
+
+    /**
+     * Switch to the buffer `count` places after (or before) the current one
+     */
+    pub fn cycle_buffer(&mut self, count: isize) -> Result<(), ExError> {
+        let ids = self.buffers.ids();
+        let idx = ids
+            .iter()
+            .position(|&id| id == self.buffers.current)
+            .unwrap_or(0);
+        let idx = (idx as isize + count).rem_euclid(ids.len() as isize) as usize;
+        self.switch_buffer(ids[idx])
+    }
+
+    /**
+     * Remove a buffer from the list. Deleting the current buffer switches
+     * to the alternate one, or any other, or a new empty buffer.
+     */
+    pub fn delete_buffer(&mut self, id: usize, force: bool) -> Result<(), ExError> {
+        if id != self.buffers.current {
+            let entry = self.buffers.get(id).ok_or(ExError::NoSuchBuffer(id))?;
+            if entry.is_modified() && !force {
+                return Err(ExError::BufferModified(id));
+            }
+            self.buffers.take(id);
+        } else {
+            if self.is_modified() && !force {
+                return Err(ExError::BufferModified(id));
+            }
+            let next = self
+                .buffers
+                .alternate
+                .or_else(|| self.buffers.hidden.first().map(|entry| entry.id));
+            let entry = match next.and_then(|next| self.buffers.take(next)) {
+                Some(entry) => entry,
+                None => BufferEntry::empty(self.buffers.next_id()),
+            };
+            self.swap_current(entry);
+            self.buffers.alternate = None;
+        }
+
+        if self.buffers.alternate == Some(id) {
+            self.buffers.alternate = None;
+        }
+        Ok(())
+    }
+
+    /**
+     * The first buffer other than the current one with unsaved changes
+     */
+    pub fn modified_hidden_buffer(&self) -> Option<usize> {
+        self.buffers
+            .hidden
+            .iter()
+            .find(|entry| entry.is_modified())
+            .map(|entry| entry.id)
+    }
+
+    /**
+     * The :ls listing, one buffer per line
+     */
+    pub fn buffer_listing(&self) -> String {
+        self.buffers
+            .ids()
+            .iter()
+            .map(|&id| {
+                let (name, line, modified, current) = match self.buffers.get(id) {
+                    Some(entry) => (
+                        &entry.filename,
+                        entry.buffer.cursor().line,
+                        entry.is_modified(),
+                        false,
+                    ),
+                    None => (
+                        &self.filename,
+                        self.text_buffer.cursor().line,
+                        self.is_modified(),
+                        true,
+                    ),
+                };
+                let flag = if current {
+                    "%a"
+                } else if self.buffers.alternate == Some(id) {
+                    "#h"
+                } else {
+                    " h"
+                };
+                format!(
+                    "{:>3} {} {} \"{}\" line {}",
+                    id,
+                    flag,
+                    if modified { "+" } else { " " },
+                    name.as_deref().unwrap_or("[No Name]"),
+                    line + 1
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /**
+     * An unnamed buffer that was never changed, which opening a file can
+     * replace instead of adding another buffer
+     */
+    fn is_scratch(&self) -> bool {
+        self.filename.is_none()
+            && !self.is_modified()
+            && self.text_buffer.text_buffer.to_string().is_empty()
+    }
+
+    /**
+     * Make the entry current, returning the previous current buffer
+     */
+    fn swap_current(&mut self, entry: BufferEntry) -> BufferEntry {
+        BufferEntry {
+            id: mem::replace(&mut self.buffers.current, entry.id),
+            buffer: mem::replace(&mut self.text_buffer, entry.buffer),
+            filename: mem::replace(&mut self.filename, entry.filename),
+            file_info: mem::replace(&mut self.file_info, entry.file_info),
+            offset: mem::replace(&mut self.text_window_state.offset, entry.offset),
+        }
+    }
+}
+
+fn file_message(filename: &str, buffer: &Buffer<PieceTableBuffer>, info: &FileInfo) -> String {
+    if info.new_file {
+        return format!("\"{}\" [New]", filename);
+    }
+    let text = buffer.text_buffer.to_string();
+    format!(
+        "\"{}\" {}{}L, {}B",
+        filename,
+        if info.readonly { "[readonly] " } else { "" },
+        text.matches('\n').count(),
+        text.len()
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::event::Event;
+    use crate::ex;
+
+    fn editor() -> Editor {
+        let mut editor = Editor::new();
+        editor.text_buffer.insert('a');
+        editor.file_info.saved_tick = editor.text_buffer.text_buffer.change_tick;
+        editor.filename = Some("one".to_string());
+
+        let buffer = Buffer::new(Box::new(PieceTableBuffer::new("b".to_string())));
+        let id = editor.buffers.next_id();
+        let entry = BufferEntry::new(id, buffer, Some("two".to_string()), FileInfo::default());
+        editor.buffers.insert(entry);
+        editor
+    }
+
+    fn text(editor: &Editor) -> String {
+        editor.text_buffer.text_buffer.to_string()
+    }
+
+    #[test]
+    fn test_switch_buffers() {
+        let mut editor = editor();
+        ex::run("bn", &mut editor).unwrap();
+        assert_eq!(text(&editor), "b");
+        assert_eq!(editor.buffers.alternate, Some(1));
+
+        ex::run("b one", &mut editor).unwrap();
+        assert_eq!(text(&editor), "a");
+        ex::run("b 2", &mut editor).unwrap();
+        assert_eq!(editor.filename.as_deref(), Some("two"));
+        ex::run("bp", &mut editor).unwrap();
+        assert_eq!(editor.buffers.current, 1);
+        assert!(ex::run("b 5", &mut editor).is_err());
+
+        // Ctrl-^ comes from the terminal as 0x1e, which termion reads as Ctrl-6
+        let key = match termion::event::parse_event(0x1e, &mut std::iter::empty()) {
+            Ok(termion::event::Event::Key(key)) => key,
+            _ => panic!("expected a key"),
+        };
+        editor.handle_event(Event::Key(key));
+        assert_eq!(editor.filename.as_deref(), Some("two"));
+    }
+
+    #[test]
+    fn test_delete_buffer() {
+        let mut editor = editor();
+        editor.text_buffer.insert('x');
+        assert!(ex::run("bd", &mut editor).is_err());
+
+        // Quitting is refused while another buffer has changes
+        ex::run("b2", &mut editor).unwrap();
+        assert!(ex::run("q", &mut editor).is_err());
+
+        ex::run("bd! 1", &mut editor).unwrap();
+        assert_eq!(editor.buffers.ids(), vec![2]);
+        ex::run("bd", &mut editor).unwrap();
+        assert_eq!(editor.buffers.ids(), vec![3]);
+        assert_eq!(text(&editor), "");
+    }
+}
@@ -0,0 +1,143 @@
+use crate::buffer::{Buffer, Position};
+use crate::editor::Editor;
+use crate::event::Event;
+use crate::file::FileInfo;
+use crate::search::Direction;
+use crate::state::{CommandState, SearchState, State};
+use crate::text::{PieceTableBuffer, TextBuffer};
+use crate::ui::text_window::TextWindowState;
+use termion::event::Key;
+
+pub const HEIGHT: u16 = 7;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CmdlineKind {
+    Command,
+    Search(Direction),
+}
+
+impl CmdlineKind {
+    pub fn prompt(&self) -> char {
+        match self {
+            CmdlineKind::Command => ':',
+            CmdlineKind::Search(direction) => direction.prompt(),
+        }
+    }
+}
+
+/**
+ * The q: and q/ window. While it's open the history is the editor's
+ * text buffer, so it can be edited like any other text, and the file
+ * being edited is kept here until the window closes.
+ */
+pub struct CmdlineWindow {
+    pub kind: CmdlineKind,
+    pub buffer: Buffer<PieceTableBuffer>,
+    pub window_state: TextWindowState,
+    pub filename: Option<String>,
+    pub file_info: FileInfo,
+}
+
+/**
+ * Open the window with the history of the given kind, one entry per line
+ * and an empty line at the bottom for a new one
+ */
+pub fn open(kind: CmdlineKind, editor: &mut Editor) {
+    if editor.cmdline_window.is_some() {
+        return;
+    }
+
+    let history = match kind {
+        CmdlineKind::Command => &editor.command_history,
+        CmdlineKind::Search(_) => &editor.search_history,
+    };
+    let text: String = history
+        .entries()
+        .iter()
+        .map(|entry| entry.clone() + "\n")
+        .collect();
+    let last = history.entries().len();
+
+    let mut buffer = Buffer::new(Box::new(PieceTableBuffer::new(text)));
+    buffer.move_cursor(Position::new(last, 0));
+    std::mem::swap(&mut editor.text_buffer, &mut buffer);
+
+    let mut window_state = TextWindowState::new();
+    std::mem::swap(&mut editor.text_window_state, &mut window_state);
+
+    editor.cmdline_window = Some(CmdlineWindow {
+        kind,
+        buffer,
+        window_state,
+        filename: editor.filename.take(),
+        file_info: std::mem::take(&mut editor.file_info),
+    });
+}
+
+/**
+ * Close the window, returning the line the cursor was on
+ */
+pub fn close(editor: &mut Editor) -> Option<(CmdlineKind, String)> {
+    let window = editor.cmdline_window.take()?;
+    let line = editor
+        .text_buffer
+        .text_buffer
+        .line(editor.text_buffer.cursor().line);
+
+    editor.text_buffer = window.buffer;
+    editor.text_window_state = window.window_state;
+    editor.filename = window.filename;
+    editor.file_info = window.file_info;
+    Some((window.kind, line))
+}
+
+/**
+ * Close the window and run the line under the cursor as if it was typed
+ * at the prompt
+ */
+pub fn run(editor: &mut Editor) -> Vec<State> {
+    let (kind, line) = match close(editor) {
+        Some(closed) => closed,
+        None => return vec![],
+    };
+
+    let enter = Event::Key(Key::Char('\n'));
+    match kind {
+        CmdlineKind::Command => {
+            let mut state = CommandState::new();
+            state.prompt.set_text(line.as_str());
+            state.handle_event(enter, editor)
+        }
+        CmdlineKind::Search(direction) => {
+            let mut state = SearchState::new(direction, editor);
+            state.prompt.set_text(line.as_str());
+            state.handle_event(enter, editor)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cmdline_window() {
+        let mut editor = Editor::new();
+        editor.text_buffer = Buffer::new(Box::new(PieceTableBuffer::new("a\nb\n".to_string())));
+        editor.command_history.push("s/a/x/");
+        editor.command_history.push("2d");
+
+        open(CmdlineKind::Command, &mut editor);
+        assert_eq!(editor.text_buffer.text_buffer.to_string(), "s/a/x/\n2d\n");
+
+        // Edit the first entry and run it
+        editor.text_buffer.move_cursor(Position::new(0, 5));
+        editor.text_buffer.delete();
+        editor.text_buffer.insert('y');
+        run(&mut editor);
+
+        assert!(editor.cmdline_window.is_none());
+        assert_eq!(editor.text_buffer.text_buffer.to_string(), "y\nb\n");
+        assert_eq!(editor.command_history.entries().last().unwrap(), "s/a/y/");
+    }
+}
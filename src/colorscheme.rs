@@ -0,0 +1,419 @@
+use crate::config::config_dir;
+use crate::ex::ExError;
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use tui::style::{Color, Modifier, Style};
+
+/**
+ * Every highlight group, as named in color schemes and :hi. The interface
+ * groups come first, then the syntax groups grammars tokenize into.
+ */
+pub const GROUPS: &[&str] = &[
+    "Normal",
+    "StatusLine",
+    "StatusLineNC",
+    "TabLine",
+    "TabLineSel",
+    "VertSplit",
+    "CursorLine",
+    "LineNr",
+    "Visual",
+    "Search",
+    "IncSearch",
+    "WildMenu",
+    "ErrorMsg",
+    "Comment",
+    "Constant",
+    "String",
+    "Number",
+    "Boolean",
+    "Identifier",
+    "Function",
+    "Keyword",
+    "Type",
+    "PreProc",
+    "Special",
+    "Title",
+    "Underlined",
+    "Bold",
+    "Italic",
+];
+
+/**
+ * Color schemes that ship with the editor. The first is loaded on startup
+ * and under every other scheme.
+ */
+const BUILTIN: &[(&str, &str)] = &[
+    ("default", include_str!("../colors/default.vik")),
+    ("dusk", include_str!("../colors/dusk.vik")),
+];
+
+const COLOR_NAMES: &[(&str, Color)] = &[
+    ("black", Color::Black),
+    ("red", Color::Red),
+    ("green", Color::Green),
+    ("yellow", Color::Yellow),
+    ("blue", Color::Blue),
+    ("magenta", Color::Magenta),
+    ("cyan", Color::Cyan),
+    ("gray", Color::Gray),
+    ("grey", Color::Gray),
+    ("darkgray", Color::DarkGray),
+    ("darkgrey", Color::DarkGray),
+    ("lightred", Color::LightRed),
+    ("lightgreen", Color::LightGreen),
+    ("lightyellow", Color::LightYellow),
+    ("lightblue", Color::LightBlue),
+    ("lightmagenta", Color::LightMagenta),
+    ("lightcyan", Color::LightCyan),
+    ("white", Color::White),
+];
+
+const MODIFIER_NAMES: &[(&str, Modifier)] = &[
+    ("bold", Modifier::BOLD),
+    ("italic", Modifier::ITALIC),
+    ("underline", Modifier::UNDERLINED),
+    ("reverse", Modifier::REVERSED),
+    ("strikethrough", Modifier::CROSSED_OUT),
+];
+
+/**
+ * How one group looks. Unset colors show whatever is underneath, which is
+ * usually Normal.
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Highlight {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub modifier: Modifier,
+}
+
+impl Highlight {
+    pub fn new() -> Self {
+        Highlight {
+            fg: None,
+            bg: None,
+            modifier: Modifier::empty(),
+        }
+    }
+
+    /**
+     * Apply `key=value` arguments: fg and bg take a color name, a 256-color
+     * number, #rrggbb or none, and style a comma separated list
+     */
+    fn set(&mut self, args: &[&str]) -> Result<(), String> {
+        for arg in args {
+            let invalid = || format!("Invalid argument: {}", arg);
+            let (key, value) = arg.split_once('=').ok_or_else(invalid)?;
+            match key {
+                "fg" => self.fg = parse_color(value).ok_or_else(invalid)?,
+                "bg" => self.bg = parse_color(value).ok_or_else(invalid)?,
+                "style" => self.modifier = parse_modifier(value).ok_or_else(invalid)?,
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for Highlight {
+    fn default() -> Self {
+        Highlight::new()
+    }
+}
+
+impl fmt::Display for Highlight {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut args = vec![];
+        if let Some(fg) = self.fg {
+            args.push(format!("fg={}", color_name(fg)));
+        }
+        if let Some(bg) = self.bg {
+            args.push(format!("bg={}", color_name(bg)));
+        }
+        if !self.modifier.is_empty() {
+            let names = MODIFIER_NAMES
+                .iter()
+                .filter(|(_, modifier)| self.modifier.contains(*modifier))
+                .map(|(name, _)| *name)
+                .collect::<Vec<&str>>();
+            args.push(format!("style={}", names.join(",")));
+        }
+
+        if args.is_empty() {
+            write!(f, "cleared")
+        } else {
+            write!(f, "{}", args.join(" "))
+        }
+    }
+}
+
+/**
+ * The style of each highlight group. Colors given as #rrggbb are matched
+ * to the nearest of the 256 colors unless the terminal has truecolor.
+ */
+#[derive(Debug, Clone)]
+pub struct Colorscheme {
+    pub name: String,
+    pub truecolor: bool,
+    groups: HashMap<&'static str, Highlight>,
+}
+
+impl Colorscheme {
+    pub fn new() -> Self {
+        let (name, text) = BUILTIN[0];
+        let colorscheme = Colorscheme {
+            name: name.to_string(),
+            truecolor: matches!(
+                env::var("COLORTERM").as_deref(),
+                Ok("truecolor") | Ok("24bit")
+            ),
+            groups: HashMap::new(),
+        };
+        colorscheme
+            .parse(text)
+            .expect("the default color scheme is valid")
+    }
+
+    /**
+     * Switch to the named scheme, from the config directory's colors/
+     * or one of the built in ones. :hi changes are dropped.
+     */
+    pub fn load(&mut self, name: &str) -> Result<(), ExError> {
+        let path = config_dir().map(|dir| dir.join("colors").join(format!("{}.vik", name)));
+        let text = match path.and_then(|path| fs::read_to_string(path).ok()) {
+            Some(text) => text,
+            None => BUILTIN
+                .iter()
+                .find(|(builtin, _)| *builtin == name)
+                .map(|(_, text)| text.to_string())
+                .ok_or_else(|| ExError::NoColorscheme(name.to_string()))?,
+        };
+
+        let mut colorscheme = Colorscheme::new()
+            .parse(text.as_str())
+            .map_err(|e| format!("Error in color scheme {}: {}", name, e))?;
+        colorscheme.name = name.to_string();
+        colorscheme.truecolor = self.truecolor;
+        *self = colorscheme;
+        Ok(())
+    }
+
+    /**
+     * Apply a scheme file on top of this one. Each line is a group and
+     * its arguments, as given to :hi. Lines starting with # are comments.
+     */
+    fn parse(mut self, text: &str) -> Result<Self, String> {
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            self.highlight(line)
+                .map_err(|e| format!("Line {}: {}", i + 1, e))?;
+        }
+        Ok(self)
+    }
+
+    /**
+     * Change a group, from :hi arguments like `Search fg=black bg=#ffaf00`
+     */
+    pub fn highlight(&mut self, args: &str) -> Result<(), String> {
+        let mut args = args.split_whitespace();
+        let name = args.next().ok_or("Missing highlight group")?;
+        let group = group_name(name).ok_or_else(|| format!("No such highlight group: {}", name))?;
+        let mut highlight = self.get(group);
+        highlight.set(&args.collect::<Vec<&str>>())?;
+        self.groups.insert(group, highlight);
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Highlight {
+        group_name(name)
+            .and_then(|group| self.groups.get(group))
+            .copied()
+            .unwrap_or_else(Highlight::new)
+    }
+
+    pub fn style(&self, name: &str) -> Style {
+        let highlight = self.get(name);
+        let mut style = Style::default().add_modifier(highlight.modifier);
+        if let Some(fg) = highlight.fg {
+            style = style.fg(self.color(fg));
+        }
+        if let Some(bg) = highlight.bg {
+            style = style.bg(self.color(bg));
+        }
+        style
+    }
+
+    /**
+     * Every group and how it looks, one per line, for :hi
+     */
+    pub fn listing(&self) -> String {
+        GROUPS
+            .iter()
+            .map(|group| format!("{:<14}{}", group, self.get(group)))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn color(&self, color: Color) -> Color {
+        match color {
+            Color::Rgb(r, g, b) if !self.truecolor => Color::Indexed(nearest_256(r, g, b)),
+            color => color,
+        }
+    }
+}
+
+impl Default for Colorscheme {
+    fn default() -> Self {
+        Colorscheme::new()
+    }
+}
+
+/**
+ * Color schemes :colorscheme can load, for completion
+ */
+pub fn names() -> Vec<String> {
+    let mut names = BUILTIN
+        .iter()
+        .map(|(name, _)| name.to_string())
+        .collect::<Vec<String>>();
+    let dir = config_dir().map(|dir| dir.join("colors"));
+    if let Some(entries) = dir.and_then(|dir| fs::read_dir(dir).ok()) {
+        names.extend(entries.filter_map(|entry| {
+            let name = entry.ok()?.file_name().into_string().ok()?;
+            name.strip_suffix(".vik").map(str::to_string)
+        }));
+    }
+    names.sort();
+    names.dedup();
+    names
+}
+
+/**
+ * Group names are matched ignoring case, like vim
+ */
+fn group_name(name: &str) -> Option<&'static str> {
+    GROUPS
+        .iter()
+        .find(|group| group.eq_ignore_ascii_case(name))
+        .copied()
+}
+
+/**
+ * A color, or None for `none`
+ */
+fn parse_color(value: &str) -> Option<Option<Color>> {
+    let value = value.to_ascii_lowercase();
+    if value == "none" {
+        return Some(None);
+    }
+    if let Some(hex) = value.strip_prefix('#') {
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        return Some(Some(Color::Rgb(channel(0)?, channel(2)?, channel(4)?)));
+    }
+    if let Ok(index) = value.parse::<u8>() {
+        return Some(Some(Color::Indexed(index)));
+    }
+    COLOR_NAMES
+        .iter()
+        .find(|(name, _)| *name == value)
+        .map(|(_, color)| Some(*color))
+}
+
+fn parse_modifier(value: &str) -> Option<Modifier> {
+    let mut modifier = Modifier::empty();
+    for name in value.split(',') {
+        if name == "none" {
+            continue;
+        }
+        let (_, flag) = MODIFIER_NAMES.iter().find(|(flag, _)| *flag == name)?;
+        modifier |= *flag;
+    }
+    Some(modifier)
+}
+
+fn color_name(color: Color) -> String {
+    match color {
+        Color::Rgb(r, g, b) => format!("#{:02x}{:02x}{:02x}", r, g, b),
+        Color::Indexed(index) => index.to_string(),
+        color => COLOR_NAMES
+            .iter()
+            .find(|(_, named)| *named == color)
+            .map_or("none", |(name, _)| name)
+            .to_string(),
+    }
+}
+
+/**
+ * The closest xterm 256-color index: either a point in the 6x6x6 color
+ * cube or one of the 24 grays
+ */
+fn nearest_256(r: u8, g: u8, b: u8) -> u8 {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let level = |c: u8| {
+        (0..6)
+            .min_by_key(|&i| (LEVELS[i] as i32 - c as i32).abs())
+            .unwrap()
+    };
+    let distance = |(r2, g2, b2): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(r, r2) + d(g, g2) + d(b, b2)
+    };
+
+    let (ri, gi, bi) = (level(r), level(g), level(b));
+    let cube = (LEVELS[ri], LEVELS[gi], LEVELS[bi]);
+    let cube_index = 16 + 36 * ri + 6 * gi + bi;
+
+    let average = (r as usize + g as usize + b as usize) / 3;
+    let gray_index = (average.saturating_sub(3) / 10).min(23);
+    let gray = 8 + 10 * gray_index as u8;
+
+    if distance((gray, gray, gray)) < distance(cube) {
+        232 + gray_index as u8
+    } else {
+        cube_index as u8
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_highlight() {
+        let mut colorscheme = Colorscheme::new();
+        colorscheme.truecolor = false;
+        assert_eq!(colorscheme.style("search").bg, Some(Color::Yellow));
+
+        colorscheme
+            .highlight("Search fg=#ffffff bg=none style=bold,underline")
+            .unwrap();
+        let highlight = colorscheme.get("Search");
+        assert_eq!(highlight.to_string(), "fg=#ffffff style=bold,underline");
+        assert_eq!(colorscheme.style("Search").fg, Some(Color::Indexed(231)));
+        colorscheme.truecolor = true;
+        assert_eq!(
+            colorscheme.style("Search").fg,
+            Some(Color::Rgb(255, 255, 255))
+        );
+
+        assert!(colorscheme.highlight("Search fg=nocolor").is_err());
+        assert!(colorscheme.highlight("Search fg=#aébcd").is_err());
+        assert!(colorscheme.highlight("Nonsense fg=red").is_err());
+        assert_eq!(nearest_256(0x80, 0x80, 0x80), 244);
+        assert_eq!(nearest_256(0xff, 0x00, 0x00), 196);
+
+        colorscheme.load("dusk").unwrap();
+        assert_eq!(colorscheme.name, "dusk");
+        assert_eq!(colorscheme.get("Bold").modifier, Modifier::BOLD);
+        assert!(colorscheme.load("nonexistent").is_err());
+    }
+}
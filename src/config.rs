@@ -0,0 +1,49 @@
+use crate::editor::Editor;
+use crate::ex::{self, ExError};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/**
+ * Where the user's configuration lives: $XDG_CONFIG_HOME/vik, or
+ * ~/.config/vik without it
+ */
+pub fn config_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("vik"))
+}
+
+/**
+ * Run a file of ex commands, one per line. Blank lines and lines starting
+ * with " are skipped. A failing command is reported and the rest still run.
+ */
+pub fn source(path: &str, editor: &mut Editor) -> Result<(), ExError> {
+    let text = fs::read_to_string(path).map_err(|e| ExError::Io(format!("{}: {}", path, e)))?;
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('"') {
+            continue;
+        }
+        if let Err(e) = ex::run(line, editor) {
+            let message = format!("Error in {} line {}: {}", path, i + 1, e);
+            editor.messages.error(message.as_str());
+        }
+    }
+    Ok(())
+}
+
+/**
+ * Run the vikrc in the config directory, if there is one
+ */
+pub fn load_vikrc(editor: &mut Editor) {
+    let path = match config_dir().map(|dir| dir.join("vikrc")) {
+        Some(path) if path.is_file() => path,
+        _ => return,
+    };
+    if let Err(e) = source(path.to_string_lossy().as_ref(), editor) {
+        editor.messages.error(e.to_string().as_str());
+    }
+}
@@ -1,17 +1,53 @@
-use crate::buffer::Buffer;
+use crate::buffer::{Buffer, Position};
+use crate::buffer_list::BufferList;
+use crate::cmdline_window::CmdlineWindow;
+use crate::colorscheme::Colorscheme;
+use crate::config;
 use crate::event::Event;
-use crate::file::load_file;
+use crate::file::FileInfo;
+use crate::history::History;
+use crate::keymap::{Keymap, Typeahead};
+use crate::message::Messages;
+use crate::options::Options;
+use crate::register::Registers;
+use crate::search::Search;
 use crate::state::{NormalState, State};
+use crate::tab::Tabs;
 use crate::text::PieceTableBuffer;
 use crate::ui::text_window::TextWindowState;
+use crate::window::Windows;
 use std::string::ToString;
+use std::time::{Duration, Instant};
+
+// How often the editor gets a tick event when nothing else happens
+const TICK: Duration = Duration::from_millis(250);
 
 pub struct Editor {
     pub text_buffer: Buffer<PieceTableBuffer>,
     pub state_stack: Vec<State>,
     pub running: bool,
+    pub buffers: BufferList,
     pub filename: Option<String>,
+    pub file_info: FileInfo,
     pub text_window_state: TextWindowState,
+    pub windows: Windows,
+    pub tabs: Tabs,
+    pub options: Options,
+    pub colorscheme: Colorscheme,
+    pub registers: Registers,
+    pub command_history: History,
+    pub search_history: History,
+    pub cmdline_window: Option<CmdlineWindow>,
+    pub messages: Messages,
+
+    // The last search, and whether its matches should still be highlighted
+    pub search: Option<Search>,
+    pub search_highlight: bool,
+
+    // Typed keys waiting on a mapping to complete, and when the last came
+    pub keymap: Keymap,
+    pub typeahead: Typeahead,
+    pub typeahead_since: Option<Instant>,
 }
 
 impl Editor {
@@ -20,8 +56,24 @@ impl Editor {
             text_buffer: Buffer::new(Box::new(PieceTableBuffer::new("".to_string()))),
             state_stack: vec![State::Normal(NormalState::new())],
             running: true,
+            buffers: BufferList::new(),
             filename: None,
+            file_info: FileInfo::default(),
             text_window_state: TextWindowState::new(),
+            windows: Windows::new(),
+            tabs: Tabs::new(),
+            options: Options::new(),
+            colorscheme: Colorscheme::new(),
+            registers: Registers::new(),
+            command_history: History::new(),
+            search_history: History::new(),
+            cmdline_window: None,
+            messages: Messages::new(),
+            search: None,
+            search_highlight: false,
+            keymap: Keymap::new(),
+            typeahead: Typeahead::new(),
+            typeahead_since: None,
         }
     }
 
@@ -29,24 +81,102 @@ impl Editor {
         match self.state() {
             State::Insert(_) => "INSERT",
             State::Command(_) => "COMMAND",
+            State::Search(_) => "SEARCH",
+            State::Substitute(_) => "SUBSTITUTE",
 
             State::Normal(_) => "NORMAL",
             State::DeleteOperator(_) => "DELETE",
+            State::Visual(_) => "VISUAL",
         }
     }
 
+    /**
+     * Whether the buffer, or the format it's written in, changed since it
+     * was last read or written
+     */
+    pub fn is_modified(&self) -> bool {
+        self.text_buffer.text_buffer.change_tick != self.file_info.saved_tick
+            || self.file_info.format != self.file_info.saved_format
+    }
+
     pub fn state(&self) -> &State {
         self.state_stack.last().unwrap()
     }
 
-    pub fn from_file(filename: String) -> Self {
+    /**
+     * Start with the vikrc run and files open, the first one current.
+     * Errors are shown as a message, leaving an empty buffer that isn't
+     * tied to the file so it can't be overwritten.
+     */
+    pub fn from_files(filenames: &[String]) -> Self {
         let mut editor = Editor::new();
-        editor.text_buffer = load_file(filename.as_str()).unwrap();
-        editor.filename = Some(filename);
+        config::load_vikrc(&mut editor);
+        let mut filenames = filenames.iter();
+        if let Some(filename) = filenames.next() {
+            if let Err(e) = editor.open_file(filename.as_str()) {
+                editor.messages.error(e.to_string().as_str());
+            }
+        }
+        for filename in filenames {
+            if let Err(e) = editor.add_file(filename.as_str()) {
+                editor.messages.error(e.to_string().as_str());
+            }
+        }
         editor
     }
 
     pub fn handle_event(&mut self, event: Event) {
+        match event {
+            Event::Key(key) => {
+                // Messages stay up until the next key
+                self.messages.clear();
+                self.handle_key(key);
+            }
+            Event::Paste(text) => {
+                self.messages.clear();
+                self.flush_pending_keys();
+
+                // Insert mode takes a paste whole, and Normal mode puts it
+                // before the cursor. Anywhere else it's dropped, so pasted
+                // text never runs as commands.
+                match self.state() {
+                    State::Insert(_) => self.dispatch(Event::Paste(text)),
+                    State::Normal(_) => {
+                        let buffer = &mut self.text_buffer;
+                        buffer.insert_str(&text);
+                        // Leave the cursor on the last char put
+                        if let Some(c) = text.chars().last().filter(|&c| c != '\n') {
+                            let cursor = buffer.cursor();
+                            buffer
+                                .move_cursor(Position::new(cursor.line, cursor.col - c.len_utf8()));
+                        }
+                    }
+                    _ => (),
+                }
+            }
+            Event::Mouse(event) => {
+                self.flush_pending_keys();
+                self.handle_mouse(event);
+            }
+            Event::Tick => self.resolve_pending_keys(),
+            // Drawing lays the windows out at the new size and scrolls each
+            // one to its cursor
+            Event::Resize(..) => (),
+        }
+    }
+
+    /**
+     * How long to wait for an event before ticking
+     */
+    pub fn next_timeout(&self) -> Duration {
+        self.pending_keys_timeout()
+            .map_or(TICK, |timeout| timeout.min(TICK))
+    }
+
+    /**
+     * Pass an event straight to the current state, without mapping keys
+     */
+    pub fn dispatch(&mut self, event: Event) {
         let state = self.state_stack.pop().unwrap();
         let mut new_states = state.handle_event(event, self);
         self.state_stack.append(&mut new_states)
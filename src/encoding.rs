@@ -0,0 +1,286 @@
+/**
+ * How lines end in a file. Buffers always use '\n' internally.
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineEnding {
+    Unix,
+    Dos,
+    Mac,
+}
+
+impl LineEnding {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "unix" => Some(LineEnding::Unix),
+            "dos" => Some(LineEnding::Dos),
+            "mac" => Some(LineEnding::Mac),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LineEnding::Unix => "unix",
+            LineEnding::Dos => "dos",
+            LineEnding::Mac => "mac",
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Unix => "\n",
+            LineEnding::Dos => "\r\n",
+            LineEnding::Mac => "\r",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Utf8,
+    Latin1,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl Encoding {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "utf-8" | "utf8" => Some(Encoding::Utf8),
+            "latin1" | "iso-8859-1" => Some(Encoding::Latin1),
+            "utf-16le" => Some(Encoding::Utf16Le),
+            "utf-16" | "utf-16be" => Some(Encoding::Utf16Be),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Latin1 => "latin1",
+            Encoding::Utf16Le => "utf-16le",
+            Encoding::Utf16Be => "utf-16",
+        }
+    }
+
+    fn bom(&self) -> &'static [u8] {
+        match self {
+            Encoding::Utf8 => &[0xef, 0xbb, 0xbf],
+            Encoding::Latin1 => &[],
+            Encoding::Utf16Le => &[0xff, 0xfe],
+            Encoding::Utf16Be => &[0xfe, 0xff],
+        }
+    }
+}
+
+/**
+ * Everything needed to write a file back the way it was read
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FileFormat {
+    pub line_ending: LineEnding,
+    pub encoding: Encoding,
+    pub bom: bool,
+}
+
+impl Default for FileFormat {
+    fn default() -> Self {
+        FileFormat {
+            line_ending: LineEnding::Unix,
+            encoding: Encoding::Utf8,
+            bom: false,
+        }
+    }
+}
+
+/**
+ * Decode file contents, detecting the encoding and line endings, and
+ * normalize them to UTF-8 with '\n' line endings
+ */
+pub fn decode(bytes: &[u8]) -> (String, FileFormat) {
+    let (text, encoding, bom) = decode_text(bytes);
+    let line_ending = detect_line_ending(text.as_str());
+    let text = match line_ending {
+        LineEnding::Unix => text,
+        LineEnding::Dos => text.replace("\r\n", "\n"),
+        LineEnding::Mac => text.replace('\r', "\n"),
+    };
+
+    let format = FileFormat {
+        line_ending,
+        encoding,
+        bom,
+    };
+    (text, format)
+}
+
+fn decode_text(bytes: &[u8]) -> (String, Encoding, bool) {
+    for &encoding in &[Encoding::Utf8, Encoding::Utf16Le, Encoding::Utf16Be] {
+        if let Some(rest) = bytes.strip_prefix(encoding.bom()) {
+            if let Some(text) = decode_as(rest, encoding) {
+                return (text, encoding, true);
+            }
+        }
+    }
+
+    // NUL bytes are valid UTF-8, but text files don't have them
+    let encoding = match guess_utf16(bytes) {
+        Some(encoding) => encoding,
+        None if std::str::from_utf8(bytes).is_ok() => Encoding::Utf8,
+        None => Encoding::Latin1,
+    };
+    match decode_as(bytes, encoding) {
+        Some(text) => (text, encoding, false),
+        None => (decode_latin1(bytes), Encoding::Latin1, false),
+    }
+}
+
+fn decode_as(bytes: &[u8], encoding: Encoding) -> Option<String> {
+    match encoding {
+        Encoding::Utf8 => String::from_utf8(bytes.to_vec()).ok(),
+        Encoding::Latin1 => Some(decode_latin1(bytes)),
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            if !bytes.len().is_multiple_of(2) {
+                return None;
+            }
+            let units = bytes
+                .chunks(2)
+                .map(|pair| match encoding {
+                    Encoding::Utf16Le => u16::from_le_bytes([pair[0], pair[1]]),
+                    _ => u16::from_be_bytes([pair[0], pair[1]]),
+                })
+                .collect::<Vec<u16>>();
+            String::from_utf16(&units).ok()
+        }
+    }
+}
+
+fn decode_latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| b as char).collect()
+}
+
+/**
+ * Mostly ASCII UTF-16 without a BOM has a zero in every other byte
+ */
+fn guess_utf16(bytes: &[u8]) -> Option<Encoding> {
+    if bytes.len() < 2 || !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+    let pairs = bytes.len() / 2;
+    let zeros_at = |start: usize| {
+        bytes
+            .iter()
+            .skip(start)
+            .step_by(2)
+            .filter(|&&b| b == 0)
+            .count()
+    };
+    if zeros_at(1) * 2 > pairs {
+        Some(Encoding::Utf16Le)
+    } else if zeros_at(0) * 2 > pairs {
+        Some(Encoding::Utf16Be)
+    } else {
+        None
+    }
+}
+
+/**
+ * Dos if every line ends in "\r\n", mac if lines only end in '\r'
+ */
+fn detect_line_ending(text: &str) -> LineEnding {
+    let newlines = text.matches('\n').count();
+    let crlfs = text.matches("\r\n").count();
+    if newlines > 0 && crlfs == newlines {
+        LineEnding::Dos
+    } else if newlines == 0 && text.contains('\r') {
+        LineEnding::Mac
+    } else {
+        LineEnding::Unix
+    }
+}
+
+/**
+ * Turn buffer text back into file contents in the given format
+ */
+pub fn encode(text: &str, format: &FileFormat) -> Result<Vec<u8>, String> {
+    let text = match format.line_ending {
+        LineEnding::Unix => text.to_string(),
+        ending => text.replace('\n', ending.as_str()),
+    };
+
+    let mut bytes = vec![];
+    if format.bom {
+        bytes.extend_from_slice(format.encoding.bom());
+    }
+    match format.encoding {
+        Encoding::Utf8 => bytes.extend_from_slice(text.as_bytes()),
+        Encoding::Latin1 => {
+            for c in text.chars() {
+                if c as u32 > 0xff {
+                    return Err(format!("Cannot convert '{}' to latin1", c));
+                }
+                bytes.push(c as u8);
+            }
+        }
+        Encoding::Utf16Le => text
+            .encode_utf16()
+            .for_each(|unit| bytes.extend_from_slice(&unit.to_le_bytes())),
+        Encoding::Utf16Be => text
+            .encode_utf16()
+            .for_each(|unit| bytes.extend_from_slice(&unit.to_be_bytes())),
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn round_trip(bytes: &[u8]) -> (String, FileFormat) {
+        let (text, format) = decode(bytes);
+        assert_eq!(encode(text.as_str(), &format).unwrap(), bytes);
+        (text, format)
+    }
+
+    #[test]
+    fn test_line_endings() {
+        let (text, format) = round_trip(b"one\r\ntwo\r\n");
+        assert_eq!(text, "one\ntwo\n");
+        assert_eq!(format.line_ending, LineEnding::Dos);
+
+        let (text, format) = round_trip(b"one\rtwo\r");
+        assert_eq!(text, "one\ntwo\n");
+        assert_eq!(format.line_ending, LineEnding::Mac);
+
+        // Mixed endings keep the stray '\r'
+        let (text, format) = round_trip(b"one\r\ntwo\n");
+        assert_eq!(text, "one\r\ntwo\n");
+        assert_eq!(format.line_ending, LineEnding::Unix);
+    }
+
+    #[test]
+    fn test_encodings() {
+        let (text, format) = round_trip(b"\xef\xbb\xbfbom\n");
+        assert_eq!(text, "bom\n");
+        assert!(format.bom);
+
+        let (text, format) = round_trip(b"caf\xe9\n");
+        assert_eq!(text, "caf\u{e9}\n");
+        assert_eq!(format.encoding, Encoding::Latin1);
+
+        let (text, format) = round_trip(b"\xff\xfeh\0i\0\n\0");
+        assert_eq!(text, "hi\n");
+        assert_eq!(format.encoding, Encoding::Utf16Le);
+
+        let (text, format) = round_trip(b"\0h\0i\0\n");
+        assert_eq!(text, "hi\n");
+        assert_eq!(format.encoding, Encoding::Utf16Be);
+        assert!(!format.bom);
+
+        let latin1 = FileFormat {
+            encoding: Encoding::Latin1,
+            ..FileFormat::default()
+        };
+        assert!(encode("\u{3bb}", &latin1).is_err());
+    }
+}
@@ -1,5 +1,165 @@
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::unix::io::FromRawFd;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
 use termion;
+use termion::event::{Key, MouseEvent};
+use termion::input::TermRead;
+
+// The pipe the SIGWINCH handler writes to, to wake the resize thread
+static RESIZE_PIPE: AtomicI32 = AtomicI32::new(-1);
+
+// What terminals send around pasted text in bracketed paste mode
+const PASTE_START: &[u8] = b"\x1b[200~";
+const PASTE_END: &[u8] = b"\x1b[201~";
 
 pub enum Event {
-    Key(termion::event::Key),
+    Key(Key),
+    // Text pasted into the terminal, all at once
+    Paste(String),
+    Mouse(MouseEvent),
+    // Nothing happened for a while
+    Tick,
+    // The terminal changed to this many columns and rows
+    Resize(u16, u16),
+}
+
+/**
+ * Terminal events. Keys and resizes are waited on by their own threads
+ * so waiting for either can time out.
+ */
+pub struct Events {
+    receiver: Receiver<Event>,
+}
+
+impl Events {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        let input = sender.clone();
+        thread::spawn(move || read_input(input));
+        watch_resize(sender);
+
+        Events { receiver }
+    }
+
+    /**
+     * Wait for the next event, or tick once the timeout passes without one
+     */
+    pub fn next(&mut self, timeout: Duration) -> Event {
+        self.receiver.recv_timeout(timeout).unwrap_or(Event::Tick)
+    }
+}
+
+impl Default for Events {
+    fn default() -> Self {
+        Events::new()
+    }
+}
+
+/**
+ * Send keys, pastes and mouse events from stdin until the receiver is gone
+ */
+fn read_input(sender: Sender<Event>) {
+    let mut paste: Option<String> = None;
+    for event in io::stdin().events().flatten() {
+        let event = match (event, &mut paste) {
+            (termion::event::Event::Unsupported(bytes), _) if bytes == PASTE_START => {
+                paste = Some(String::new());
+                continue;
+            }
+            (termion::event::Event::Unsupported(bytes), _) if bytes == PASTE_END => {
+                Event::Paste(paste.take().unwrap_or_default())
+            }
+            (termion::event::Event::Key(Key::Char(c)), Some(text)) => {
+                text.push(c);
+                continue;
+            }
+            (termion::event::Event::Key(key), None) => Event::Key(key),
+            (termion::event::Event::Mouse(event), None) => Event::Mouse(event),
+            _ => continue,
+        };
+        if sender.send(event).is_err() {
+            break;
+        }
+    }
+}
+
+extern "C" fn on_resize(_: libc::c_int) {
+    // Only async-signal-safe calls here, so just wake the resize thread
+    let fd = RESIZE_PIPE.load(Ordering::Relaxed);
+    unsafe {
+        libc::write(fd, [0u8].as_ptr() as *const libc::c_void, 1);
+    }
+}
+
+/**
+ * Send a resize event with the new size whenever SIGWINCH arrives
+ */
+fn watch_resize(sender: Sender<Event>) {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        return;
+    }
+    RESIZE_PIPE.store(fds[1], Ordering::Relaxed);
+    unsafe {
+        // A full pipe already has a wake up waiting, so the handler
+        // mustn't block writing another
+        let flags = libc::fcntl(fds[1], libc::F_GETFL);
+        libc::fcntl(fds[1], libc::F_SETFL, flags | libc::O_NONBLOCK);
+        libc::signal(libc::SIGWINCH, on_resize as *const () as libc::sighandler_t);
+    }
+
+    let mut pipe = unsafe { File::from_raw_fd(fds[0]) };
+    thread::spawn(move || {
+        let mut buf = [0u8; 16];
+        loop {
+            match pipe.read(&mut buf) {
+                Ok(0) => break,
+                Ok(_) => (),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(_) => break,
+            }
+            if let Ok((width, height)) = termion::terminal_size() {
+                if sender.send(Event::Resize(width, height)).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+}
+
+/**
+ * A terminal in bracketed paste mode, so pastes can be told apart from
+ * typing. The mode is turned off again when it's dropped.
+ */
+pub struct PasteTerminal<W: Write> {
+    terminal: W,
+}
+
+impl<W: Write> From<W> for PasteTerminal<W> {
+    fn from(mut terminal: W) -> Self {
+        let _ = terminal.write_all(b"\x1b[?2004h");
+        PasteTerminal { terminal }
+    }
+}
+
+impl<W: Write> Drop for PasteTerminal<W> {
+    fn drop(&mut self) {
+        let _ = self.terminal.write_all(b"\x1b[?2004l");
+        let _ = self.terminal.flush();
+    }
+}
+
+impl<W: Write> Write for PasteTerminal<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.terminal.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.terminal.flush()
+    }
 }
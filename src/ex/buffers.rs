@@ -0,0 +1,69 @@
+use super::{check_cmdline_window, ExCommand, ExError, ExResult};
+use crate::editor::Editor;
+
+/**
+ * The buffer an argument names: its number, or a unique part of its
+ * file name. No argument means the current buffer.
+ */
+fn find_buffer(arg: &str, editor: &Editor) -> Result<usize, ExError> {
+    let arg = arg.trim();
+    if arg.is_empty() {
+        return Ok(editor.buffers.current);
+    }
+    if let Ok(id) = arg.parse::<usize>() {
+        return Ok(id);
+    }
+
+    let matches = editor
+        .buffers
+        .ids()
+        .into_iter()
+        .filter(|&id| {
+            let name = match editor.buffers.get(id) {
+                Some(entry) => &entry.filename,
+                None => &editor.filename,
+            };
+            name.as_deref().is_some_and(|name| name.contains(arg))
+        })
+        .collect::<Vec<usize>>();
+    match matches.as_slice() {
+        [id] => Ok(*id),
+        [] => Err(ExError::Invalid(format!("No matching buffer for {}", arg))),
+        _ => Err(ExError::Invalid(format!("More than one match for {}", arg))),
+    }
+}
+
+pub fn buffer(command: &ExCommand, editor: &mut Editor) -> ExResult {
+    check_cmdline_window(editor)?;
+    let id = find_buffer(command.args.as_str(), editor)?;
+    editor.switch_buffer(id)?;
+    Ok(vec![])
+}
+
+/**
+ * :bn and :bp, with an optional count
+ */
+pub fn cycle(command: &ExCommand, editor: &mut Editor, forward: bool) -> ExResult {
+    check_cmdline_window(editor)?;
+    let count = match command.args.trim() {
+        "" => 1,
+        count => count
+            .parse::<isize>()
+            .map_err(|_| format!("Invalid argument: {}", count))?,
+    };
+    editor.cycle_buffer(if forward { count } else { -count })?;
+    Ok(vec![])
+}
+
+pub fn delete(command: &ExCommand, editor: &mut Editor) -> ExResult {
+    check_cmdline_window(editor)?;
+    let id = find_buffer(command.args.as_str(), editor)?;
+    editor.delete_buffer(id, command.bang)?;
+    Ok(vec![])
+}
+
+pub fn list(editor: &mut Editor) -> ExResult {
+    let listing = editor.buffer_listing();
+    editor.messages.show_only(listing.as_str());
+    Ok(vec![])
+}
@@ -0,0 +1,182 @@
+use super::parser::{resolve_name, COMMANDS};
+use super::range::Range;
+use crate::colorscheme;
+use crate::editor::Editor;
+use crate::options;
+use std::fs;
+use std::path::Path;
+
+/**
+ * Candidates for the word before the cursor. Tab cycles through them,
+ * ending back on what was originally typed.
+ */
+#[derive(Debug, Clone)]
+pub struct Completion {
+    pub candidates: Vec<String>,
+    pub selected: Option<usize>,
+    // Where the completed word starts in the command line
+    pub start: usize,
+    original: String,
+}
+
+impl Completion {
+    fn new(candidates: Vec<String>, start: usize, original: &str) -> Option<Self> {
+        if candidates.is_empty() {
+            return None;
+        }
+        Some(Completion {
+            candidates,
+            selected: None,
+            start,
+            original: original.to_string(),
+        })
+    }
+
+    /**
+     * Select the next (or previous) candidate and return the text to use
+     */
+    pub fn step(&mut self, forward: bool) -> &str {
+        let count = self.candidates.len();
+        self.selected = match (self.selected, forward) {
+            (None, true) => Some(0),
+            (None, false) => Some(count - 1),
+            (Some(i), true) if i + 1 < count => Some(i + 1),
+            (Some(i), false) if i > 0 => Some(i - 1),
+            _ => None,
+        };
+
+        match self.selected {
+            Some(i) => self.candidates[i].as_str(),
+            None => self.original.as_str(),
+        }
+    }
+}
+
+/**
+ * Complete the end of a partial command line: the command name, or its
+ * argument for commands that take files, options or buffers.
+ */
+pub fn complete(line: &str, editor: &Editor) -> Option<Completion> {
+    let trimmed = line.trim_start_matches(|c: char| c == ':' || c.is_whitespace());
+    let rest = match Range::parse(trimmed) {
+        Some((_, rest)) => rest.trim_start(),
+        None => trimmed,
+    };
+
+    let name_len = rest.chars().take_while(|c| c.is_ascii_alphabetic()).count();
+    let (name, args) = rest.split_at(name_len);
+    if args.is_empty() {
+        let candidates = COMMANDS
+            .iter()
+            .map(|(full, _)| full.to_string())
+            .filter(|full| full.starts_with(name))
+            .collect();
+        return Completion::new(candidates, line.len() - rest.len(), name);
+    }
+
+    let args = args.strip_prefix('!').unwrap_or(args);
+    if !args.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let start = line.rfind(char::is_whitespace).map_or(0, |idx| idx + 1);
+    let word = &line[start..];
+
+    let candidates = match resolve_name(name).as_str() {
+        "edit" | "write" | "split" | "vsplit" | "tabnew" | "source" => complete_path(word),
+        "set" | "setlocal" => complete_option(word),
+        "buffer" | "bdelete" => complete_buffer(word, editor),
+        "colorscheme" => complete_name(colorscheme::names(), word),
+        "highlight" => complete_name(colorscheme::GROUPS.iter().map(|g| g.to_string()), word),
+        _ => return None,
+    };
+    Completion::new(candidates, start, word)
+}
+
+fn complete_path(word: &str) -> Vec<String> {
+    let (dir, prefix) = match word.rfind('/') {
+        Some(idx) => word.split_at(idx + 1),
+        None => ("", word),
+    };
+    let entries = match fs::read_dir(if dir.is_empty() { "." } else { dir }) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+
+    let mut candidates = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            // Hidden files only when asked for
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let suffix = if Path::new(dir).join(&name).is_dir() {
+                "/"
+            } else {
+                ""
+            };
+            Some(format!("{}{}{}", dir, name, suffix))
+        })
+        .collect::<Vec<String>>();
+    candidates.sort();
+    candidates
+}
+
+fn complete_option(word: &str) -> Vec<String> {
+    let (negation, prefix) = match word.strip_prefix("no") {
+        Some(prefix) => ("no", prefix),
+        None => ("", word),
+    };
+    options::OPTIONS
+        .iter()
+        .map(|(name, _, _)| name)
+        .filter(|name| name.starts_with(prefix))
+        .map(|name| format!("{}{}", negation, name))
+        .collect()
+}
+
+fn complete_name<I: IntoIterator<Item = String>>(names: I, word: &str) -> Vec<String> {
+    names
+        .into_iter()
+        .filter(|name| name.starts_with(word))
+        .collect()
+}
+
+fn complete_buffer(word: &str, editor: &Editor) -> Vec<String> {
+    let hidden = editor.buffers.hidden().iter().map(|entry| &entry.filename);
+    let mut names = std::iter::once(&editor.filename)
+        .chain(hidden)
+        .filter_map(|name| name.clone())
+        .filter(|name| name.contains(word))
+        .collect::<Vec<String>>();
+    names.sort();
+    names
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn candidates(line: &str) -> Vec<String> {
+        complete(line, &Editor::new()).map_or(vec![], |c| c.candidates)
+    }
+
+    #[test]
+    fn test_complete_command() {
+        assert_eq!(candidates("no"), vec!["nohlsearch", "noremap", "normal"]);
+        assert_eq!(candidates("%su"), vec!["substitute"]);
+        assert_eq!(candidates("set hl"), vec!["hlsearch"]);
+        assert_eq!(candidates("se noinc"), vec!["noincsearch"]);
+        assert_eq!(candidates("e src/ma"), vec!["src/main.rs"]);
+        assert!(candidates("e src/").contains(&"src/ex/".to_string()));
+    }
+
+    #[test]
+    fn test_step() {
+        let mut completion = complete("set sh", &Editor::new()).unwrap();
+        assert_eq!(completion.candidates, vec!["shiftwidth"]);
+        assert_eq!(completion.step(true), "shiftwidth");
+        assert_eq!(completion.step(true), "sh");
+        assert_eq!(completion.step(false), "shiftwidth");
+    }
+}
@@ -0,0 +1,270 @@
+use super::range::Address;
+use super::{line_range, ExCommand, ExResult, LineRange};
+use crate::buffer::Position;
+use crate::editor::Editor;
+use crate::register::Register;
+use crate::text::TextBuffer;
+use termion::event::Key;
+
+/**
+ * Parse the `[x] [count]` arguments of :d, :y, :> and :<. A count makes
+ * the range start at its last line and cover count lines.
+ */
+fn register_and_count(
+    command: &ExCommand,
+    editor: &Editor,
+    with_register: bool,
+) -> Result<(LineRange, Option<char>), String> {
+    let mut range = line_range(command, editor)?;
+    let mut args = command.args.as_str().trim();
+
+    let mut register = None;
+    if with_register {
+        if let Some(c) = args.chars().next().filter(|c| !c.is_ascii_digit()) {
+            register = Some(c);
+            args = args[c.len_utf8()..].trim_start();
+        }
+    }
+
+    if !args.is_empty() {
+        let count = args
+            .parse::<usize>()
+            .map_err(|_| format!("Trailing characters: {}", args))?;
+        if count == 0 {
+            return Err("Positive count required".to_string());
+        }
+        let last = super::last_line(editor.text_buffer.text_buffer.as_ref());
+        range = LineRange::new(range.end, (range.end + count - 1).min(last));
+    }
+    Ok((range, register))
+}
+
+/**
+ * Resolve the destination address of :m and :t
+ */
+fn destination(command: &ExCommand, editor: &Editor) -> Result<usize, String> {
+    let current = editor.text_buffer.cursor().line + 1;
+    match Address::parse(command.args.trim()) {
+        Some((address, rest)) if rest.trim().is_empty() => address.resolve(editor, current),
+        _ => Err("Invalid address".to_string()),
+    }
+}
+
+pub fn delete(command: &ExCommand, editor: &mut Editor) -> ExResult {
+    let (range, register) = register_and_count(command, editor, true)?;
+
+    let group = editor.text_buffer.text_buffer.start_undo_group();
+    let removed = editor.text_buffer.delete_lines(range.start, range.end);
+    editor.text_buffer.text_buffer.end_undo_group(group);
+
+    editor.registers.set(register, Register::new(removed, true));
+    Ok(vec![])
+}
+
+pub fn yank(command: &ExCommand, editor: &mut Editor) -> ExResult {
+    let (range, register) = register_and_count(command, editor, true)?;
+    let text = editor.text_buffer.lines(range.start, range.end);
+    editor.registers.set(register, Register::new(text, true));
+    Ok(vec![])
+}
+
+pub fn move_lines(command: &ExCommand, editor: &mut Editor) -> ExResult {
+    let range = line_range(command, editor)?;
+    let dest = destination(command, editor)?;
+    if dest > range.start && dest <= range.end {
+        return Err("Cannot move a range of lines into itself".into());
+    }
+
+    let count = range.end - range.start + 1;
+    let group = editor.text_buffer.text_buffer.start_undo_group();
+
+    let lines = editor.text_buffer.delete_lines(range.start, range.end);
+    let dest = if dest > range.end { dest - count } else { dest };
+    editor.text_buffer.insert_lines(dest, lines.as_str());
+
+    editor.text_buffer.text_buffer.end_undo_group(group);
+    editor
+        .text_buffer
+        .move_cursor(Position::new(dest + count - 1, 0));
+    Ok(vec![])
+}
+
+pub fn copy_lines(command: &ExCommand, editor: &mut Editor) -> ExResult {
+    let range = line_range(command, editor)?;
+    let dest = destination(command, editor)?;
+
+    let count = range.end - range.start + 1;
+    let lines = editor.text_buffer.lines(range.start, range.end);
+    let group = editor.text_buffer.text_buffer.start_undo_group();
+    editor.text_buffer.insert_lines(dest, lines.as_str());
+    editor.text_buffer.text_buffer.end_undo_group(group);
+
+    editor
+        .text_buffer
+        .move_cursor(Position::new(dest + count - 1, 0));
+    Ok(vec![])
+}
+
+/**
+ * Join the lines in the range. A single line joins with the next one.
+ * Without a bang, leading whitespace is replaced by a single space.
+ */
+pub fn join(command: &ExCommand, editor: &mut Editor) -> ExResult {
+    let range = line_range(command, editor)?;
+    let last = super::last_line(editor.text_buffer.text_buffer.as_ref());
+    let end = if range.start == range.end {
+        range.end + 1
+    } else {
+        range.end
+    };
+    let end = end.min(last);
+    if end <= range.start {
+        return Ok(vec![]);
+    }
+
+    let mut joined = editor.text_buffer.text_buffer.line(range.start);
+    for line in range.start + 1..=end {
+        let text = editor.text_buffer.text_buffer.line(line);
+        if command.bang {
+            joined.push_str(text.as_str());
+            continue;
+        }
+
+        let text = text.trim_start();
+        if !joined.is_empty() && !text.is_empty() && !joined.ends_with(char::is_whitespace) {
+            joined.push(' ');
+        }
+        joined.push_str(text);
+    }
+
+    let group = editor.text_buffer.text_buffer.start_undo_group();
+    editor
+        .text_buffer
+        .replace_lines(range.start, end, (joined + "\n").as_str());
+    editor.text_buffer.text_buffer.end_undo_group(group);
+
+    editor
+        .text_buffer
+        .move_cursor(Position::new(range.start, 0));
+    Ok(vec![])
+}
+
+/**
+ * :> and :<. Each extra > or < in the name shifts by another shiftwidth.
+ */
+pub fn shift(command: &ExCommand, editor: &mut Editor) -> ExResult {
+    let (range, _) = register_and_count(command, editor, false)?;
+    let width = editor.text_buffer.options.shiftwidth * command.name.len();
+    let right = command.name.starts_with('>');
+
+    let mut shifted = String::new();
+    for line in range.start..=range.end {
+        let text = editor.text_buffer.text_buffer.line(line);
+        if right {
+            if !text.is_empty() {
+                shifted.push_str(" ".repeat(width).as_str());
+            }
+            shifted.push_str(text.as_str());
+        } else {
+            // Tabs count as a full shiftwidth
+            let mut removed = 0;
+            let rest = text.trim_start_matches(|c| {
+                let size = match c {
+                    ' ' => 1,
+                    '\t' => editor.text_buffer.options.shiftwidth,
+                    _ => return false,
+                };
+                removed += size;
+                removed <= width
+            });
+            shifted.push_str(rest);
+        }
+        shifted.push('\n');
+    }
+
+    let group = editor.text_buffer.text_buffer.start_undo_group();
+    editor
+        .text_buffer
+        .replace_lines(range.start, range.end, shifted.as_str());
+    editor.text_buffer.text_buffer.end_undo_group(group);
+
+    editor.text_buffer.move_cursor(Position::new(range.end, 0));
+    Ok(vec![])
+}
+
+/**
+ * Replay the arguments as normal mode keys on each line in the range.
+ * Anything left unfinished, like an insert, is escaped after each line.
+ * Mappings apply unless :normal!.
+ */
+pub fn normal(command: &ExCommand, editor: &mut Editor) -> ExResult {
+    let range = line_range(command, editor)?;
+    let depth = editor.state_stack.len();
+    let keys = command.args.chars().map(Key::Char).collect::<Vec<Key>>();
+
+    let group = editor.text_buffer.text_buffer.start_undo_group();
+
+    let mut line = range.start as isize;
+    let mut end = range.end as isize;
+    while line <= end && editor.running {
+        let before = editor.text_buffer.text_buffer.line_count() as isize;
+        editor
+            .text_buffer
+            .move_cursor(Position::new(line as usize, 0));
+
+        editor.execute_keys(&keys, !command.bang);
+        while editor.state_stack.len() > depth {
+            editor.execute_keys(&[Key::Esc], false);
+        }
+
+        // Keep following the same lines if the keys added or removed some
+        let delta = editor.text_buffer.text_buffer.line_count() as isize - before;
+        end += delta;
+        line += (1 + delta).max(0);
+    }
+
+    editor.text_buffer.text_buffer.end_undo_group(group);
+    Ok(vec![])
+}
+
+#[cfg(test)]
+mod test {
+    use crate::buffer::Buffer;
+    use crate::editor::Editor;
+    use crate::ex;
+    use crate::text::{PieceTableBuffer, TextBuffer};
+
+    fn run(text: &str, commands: &[&str]) -> String {
+        let mut editor = Editor::new();
+        editor.text_buffer = Buffer::new(Box::new(PieceTableBuffer::new(text.to_string())));
+        for command in commands {
+            ex::run(command, &mut editor).unwrap();
+        }
+        editor.text_buffer.text_buffer.to_string()
+    }
+
+    #[test]
+    fn test_move_and_copy() {
+        let text = "a\nb\nc\nd\n";
+        assert_eq!(run(text, &["1,2m$"]), "c\nd\na\nb\n");
+        assert_eq!(run(text, &["4m0"]), "d\na\nb\nc\n");
+        assert_eq!(run(text, &["2t."]), "a\nb\nb\nc\nd\n");
+        assert_eq!(run(text, &["1,2co$"]), "a\nb\nc\nd\na\nb\n");
+    }
+
+    #[test]
+    fn test_delete_and_join() {
+        let text = "one\n  two\nthree\n";
+        assert_eq!(run(text, &["2d"]), "one\nthree\n");
+        assert_eq!(run(text, &["1j"]), "one two\nthree\n");
+        assert_eq!(run(text, &["%j!"]), "one  twothree\n");
+    }
+
+    #[test]
+    fn test_shift_and_normal() {
+        let text = "one\n\ntwo\n";
+        assert_eq!(run(text, &["%>"]), "    one\n\n    two\n");
+        assert_eq!(run(text, &["%>>", "1<"]), "    one\n\n        two\n");
+        assert_eq!(run(text, &["%norm i;"]), ";one\n;\n;two\n");
+    }
+}
@@ -0,0 +1,90 @@
+use std::error::Error;
+use std::fmt;
+
+/**
+ * Why an ex command failed. Shown to the user in the message area.
+ */
+#[derive(Debug)]
+pub enum ExError {
+    NotACommand(String),
+    NoFileName,
+    FileExists,
+    ChangedOnDisk,
+    ReadOnly,
+    NoWriteSinceChange,
+    BufferModified(usize),
+    NoSuchBuffer(usize),
+    NoAlternateFile,
+    CmdlineWindow,
+    LastWindow,
+    NoSuchTab(usize),
+    LastTab,
+    NoColorscheme(String),
+    UnknownOption(String),
+    PatternNotFound(String),
+    Regex(regex::Error),
+    Io(String),
+    // Bad ranges, arguments and anything else a command rejects
+    Invalid(String),
+}
+
+impl fmt::Display for ExError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExError::NotACommand(name) => write!(f, "Not an editor command: {}", name),
+            ExError::NoFileName => write!(f, "No file name"),
+            ExError::FileExists => write!(f, "File exists (add ! to override)"),
+            ExError::ChangedOnDisk => write!(
+                f,
+                "File changed on disk since it was read (add ! to override)"
+            ),
+            ExError::NoWriteSinceChange => {
+                write!(f, "No write since last change (add ! to override)")
+            }
+            ExError::ReadOnly => write!(f, "File is read-only (add ! to override)"),
+            ExError::BufferModified(id) => write!(
+                f,
+                "No write since last change for buffer {} (add ! to override)",
+                id
+            ),
+            ExError::NoSuchBuffer(id) => write!(f, "Buffer {} does not exist", id),
+            ExError::NoAlternateFile => write!(f, "No alternate file"),
+            ExError::CmdlineWindow => write!(f, "Invalid in command-line window"),
+            ExError::LastWindow => write!(f, "Cannot close last window"),
+            ExError::NoSuchTab(n) => write!(f, "Tab page {} does not exist", n),
+            ExError::LastTab => write!(f, "Cannot close last tab page"),
+            ExError::NoColorscheme(name) => write!(f, "Cannot find color scheme '{}'", name),
+            ExError::UnknownOption(name) => write!(f, "Unknown option: {}", name),
+            ExError::PatternNotFound(pattern) => write!(f, "Pattern not found: {}", pattern),
+            ExError::Regex(e) => write!(f, "Invalid pattern: {}", e),
+            ExError::Io(e) => write!(f, "{}", e),
+            ExError::Invalid(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for ExError {}
+
+impl From<String> for ExError {
+    fn from(e: String) -> Self {
+        ExError::Invalid(e)
+    }
+}
+
+impl From<&str> for ExError {
+    fn from(e: &str) -> Self {
+        ExError::Invalid(e.to_string())
+    }
+}
+
+impl From<regex::Error> for ExError {
+    fn from(e: regex::Error) -> Self {
+        ExError::Regex(e)
+    }
+}
+
+impl From<Box<dyn Error + 'static>> for ExError {
+    fn from(e: Box<dyn Error + 'static>) -> Self {
+        ExError::Io(e.to_string())
+    }
+}
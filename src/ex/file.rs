@@ -0,0 +1,134 @@
+use super::{check_cmdline_window, ExCommand, ExError, ExResult};
+use crate::editor::Editor;
+use crate::file::write_file;
+use crate::syntax::Highlighter;
+use crate::text::TextBuffer;
+use std::path::Path;
+
+/**
+ * :w [file]. Without a bang, refuses to overwrite some other existing
+ * file, or the current one if it changed on disk since it was read.
+ */
+pub fn write(command: &ExCommand, editor: &mut Editor) -> ExResult {
+    let target = command.args.trim();
+    let filename = match (target, &editor.filename) {
+        ("", Some(filename)) => filename.clone(),
+        ("", None) => return Err(ExError::NoFileName),
+        (target, _) => target.to_string(),
+    };
+
+    let is_current = editor.filename.as_deref() == Some(filename.as_str());
+    if !command.bang {
+        if !is_current && Path::new(&filename).exists() {
+            return Err(ExError::FileExists);
+        }
+        if is_current && editor.file_info.readonly {
+            return Err(ExError::ReadOnly);
+        }
+        if is_current && editor.file_info.changed_on_disk(filename.as_str()) {
+            return Err(ExError::ChangedOnDisk);
+        }
+    }
+
+    let info = write_file(
+        filename.as_str(),
+        &editor.text_buffer,
+        editor.file_info.format,
+        &editor.options,
+    )?;
+
+    // Writing an unnamed buffer names it
+    if editor.filename.is_none() {
+        editor.filename = Some(filename.clone());
+        editor.text_buffer.syntax = Highlighter::for_file(filename.as_str());
+    }
+    if editor.filename.as_deref() == Some(filename.as_str()) {
+        editor.file_info = info;
+    }
+
+    let text = editor.text_buffer.text_buffer.to_string();
+    let message = format!(
+        "\"{}\" {}L, {}B written",
+        filename,
+        text.matches('\n').count(),
+        text.len()
+    );
+    editor.messages.info(message.as_str());
+    Ok(vec![])
+}
+
+/**
+ * :e [file]. Without a file, reloads the current one. Refuses to drop
+ * unsaved changes without a bang.
+ */
+pub fn edit(command: &ExCommand, editor: &mut Editor) -> ExResult {
+    check_cmdline_window(editor)?;
+    let filename = match (command.args.trim(), &editor.filename) {
+        ("", Some(filename)) => filename.clone(),
+        ("", None) => return Err(ExError::NoFileName),
+        (filename, _) => filename.to_string(),
+    };
+    if editor.is_modified() && !command.bang {
+        return Err(ExError::NoWriteSinceChange);
+    }
+
+    editor.open_file(filename.as_str())?;
+    Ok(vec![])
+}
+
+/**
+ * Quit unless some buffer has unsaved changes
+ */
+fn quit_unless_modified(editor: &mut Editor) -> ExResult {
+    if let Some(id) = editor.modified_hidden_buffer() {
+        return Err(ExError::BufferModified(id));
+    }
+    editor.running = false;
+    Ok(vec![])
+}
+
+fn has_other_windows(editor: &Editor) -> bool {
+    editor.windows.count() > 1 || editor.tabs.count() > 1
+}
+
+/**
+ * Close the current window, or quit if it's the last one. The bang quits
+ * even with other modified buffers.
+ */
+fn close_or_quit(command: &ExCommand, editor: &mut Editor) -> ExResult {
+    // With other windows or tab pages open, only the current window closes
+    if has_other_windows(editor) {
+        editor.close_window()?;
+        return Ok(vec![]);
+    }
+    if command.bang {
+        editor.running = false;
+        return Ok(vec![]);
+    }
+    quit_unless_modified(editor)
+}
+
+pub fn quit(command: &ExCommand, editor: &mut Editor) -> ExResult {
+    if editor.is_modified() && !command.bang && !has_other_windows(editor) {
+        return Err(ExError::NoWriteSinceChange);
+    }
+    close_or_quit(command, editor)
+}
+
+/**
+ * :wq. The bang applies to the write, and to other modified buffers.
+ */
+pub fn write_quit(command: &ExCommand, editor: &mut Editor) -> ExResult {
+    write(command, editor)?;
+    close_or_quit(command, editor)
+}
+
+/**
+ * :x. Like :wq, but only writes when there are changes.
+ */
+pub fn exit(command: &ExCommand, editor: &mut Editor) -> ExResult {
+    if editor.is_modified() || !command.args.trim().is_empty() {
+        write(command, editor)?;
+    }
+    close_or_quit(command, editor)
+}
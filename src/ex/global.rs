@@ -0,0 +1,113 @@
+use super::range::{parse_pattern, Range};
+use super::{execute, ExCommand, ExResult, LineRange};
+use crate::buffer::Position;
+use crate::editor::Editor;
+use crate::text::{Anchor, TextBuffer};
+use regex::{Regex, RegexBuilder};
+
+/**
+ * :g/pat/cmd and :v/pat/cmd (or :g!). Every matching line is anchored
+ * first, then the command runs on each line that still exists, so it can
+ * freely add and remove lines. The whole run is undone as one step.
+ */
+pub fn global(command: &ExCommand, editor: &mut Editor) -> ExResult {
+    let invert = command.bang || command.name == "vglobal";
+
+    let delimiter = command
+        .args
+        .chars()
+        .next()
+        .filter(|c| !c.is_alphanumeric() && !c.is_whitespace() && *c != '\\')
+        .ok_or("Regular expression missing from :global")?;
+    let (pattern, sub_command) = parse_pattern(&command.args[delimiter.len_utf8()..], delimiter);
+
+    let regex = if pattern.is_empty() {
+        editor
+            .search
+            .as_ref()
+            .map(|search| search.regex.clone())
+            .ok_or("No previous regular expression")?
+    } else {
+        RegexBuilder::new(pattern.as_str())
+            .case_insensitive(editor.options.ignore_case(pattern.as_str()))
+            .build()?
+    };
+
+    let sub_command = ExCommand::parse(sub_command)?;
+    if sub_command.name == "global" || sub_command.name == "vglobal" {
+        return Err("Cannot do :global recursive".into());
+    }
+
+    let range = match &command.range {
+        Some(range) => range.resolve(editor)?,
+        None => Range::Entire.resolve(editor)?,
+    };
+    let anchors = mark_lines(editor, range, &regex, invert);
+
+    let group = editor.text_buffer.text_buffer.start_undo_group();
+    let mut result = Ok(vec![]);
+    for anchor in anchors {
+        let line = match editor.text_buffer.text_buffer.anchor_line(&anchor) {
+            Some(line) => line,
+            None => continue,
+        };
+
+        editor.text_buffer.move_cursor(Position::new(line, 0));
+        if let Err(e) = execute(&sub_command, editor) {
+            result = Err(e);
+            break;
+        }
+    }
+    editor.text_buffer.text_buffer.end_undo_group(group);
+
+    result
+}
+
+fn mark_lines(editor: &Editor, range: LineRange, regex: &Regex, invert: bool) -> Vec<Anchor> {
+    let text = editor.text_buffer.text_buffer.as_ref();
+    (range.start..=range.end)
+        .filter(|&line| regex.is_match(text.line(line).as_str()) != invert)
+        .filter_map(|line| text.line_anchor(line))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::buffer::Buffer;
+    use crate::editor::Editor;
+    use crate::ex;
+    use crate::text::{PieceTableBuffer, TextBuffer};
+
+    fn run(text: &str, command: &str) -> String {
+        let mut editor = Editor::new();
+        editor.text_buffer = Buffer::new(Box::new(PieceTableBuffer::new(text.to_string())));
+        ex::run(command, &mut editor).unwrap();
+        editor.text_buffer.text_buffer.to_string()
+    }
+
+    #[test]
+    fn test_global() {
+        let text = "foo 1\nbar 2\nfoo 3\nbaz 4\n";
+        assert_eq!(run(text, "g/foo/d"), "bar 2\nbaz 4\n");
+        assert_eq!(run(text, "v/foo/d"), "foo 1\nfoo 3\n");
+        assert_eq!(run(text, "g!/foo/s/ba/BA/"), "foo 1\nBAr 2\nfoo 3\nBAz 4\n");
+        assert_eq!(run(text, "g/^/m0"), "baz 4\nfoo 3\nbar 2\nfoo 1\n");
+        assert_eq!(
+            run(text, "g/foo/t."),
+            "foo 1\nfoo 1\nbar 2\nfoo 3\nfoo 3\nbaz 4\n"
+        );
+        assert_eq!(run(text, "2,$g/foo/j"), "foo 1\nbar 2\nfoo 3 baz 4\n");
+    }
+
+    #[test]
+    fn test_global_undo() {
+        let mut editor = Editor::new();
+        let text = "a\nb\na\n";
+        editor.text_buffer = Buffer::new(Box::new(PieceTableBuffer::new(text.to_string())));
+        ex::run("g/a/norm ix", &mut editor).unwrap();
+        assert_eq!(editor.text_buffer.text_buffer.to_string(), "xa\nb\nxa\n");
+
+        editor.text_buffer.text_buffer.undo();
+        assert_eq!(editor.text_buffer.text_buffer.to_string(), text);
+    }
+}
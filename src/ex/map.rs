@@ -0,0 +1,71 @@
+use super::{ExCommand, ExError, ExResult};
+use crate::editor::Editor;
+use crate::keymap::{parse_keys, Mapping, Mode};
+
+/**
+ * The modes a map command applies to. :map and friends cover normal and
+ * visual mode, or insert mode with a bang.
+ */
+fn modes(command: &ExCommand) -> &'static [Mode] {
+    match command.name.trim_start_matches("un").chars().next() {
+        Some('n') if command.name != "noremap" => &[Mode::Normal],
+        Some('v') => &[Mode::Visual],
+        Some('i') => &[Mode::Insert],
+        _ if command.bang => &[Mode::Insert],
+        _ => &[Mode::Normal, Mode::Visual],
+    }
+}
+
+/**
+ * :map {lhs} {rhs} maps keys, and the noremap variants map them without
+ * remapping the result. With only {lhs}, lists the mappings starting
+ * with it, and with no arguments lists them all.
+ */
+pub fn map(command: &ExCommand, editor: &mut Editor) -> ExResult {
+    let modes = modes(command);
+    let args = command.args.trim_start();
+    let (lhs, rhs) = match args.find(char::is_whitespace) {
+        Some(idx) => (&args[..idx], args[idx..].trim_start()),
+        None => (args, ""),
+    };
+    let lhs = parse_keys(lhs, editor.options.mapleader.as_str());
+
+    if rhs.is_empty() {
+        let listing = editor.keymap.listing(modes, &lhs);
+        if listing.is_empty() {
+            editor.messages.info("No mapping found");
+        } else {
+            editor.messages.show_only(listing.join("\n").as_str());
+        }
+        return Ok(vec![]);
+    }
+
+    let rhs = parse_keys(rhs, editor.options.mapleader.as_str());
+    for &mode in modes {
+        editor.keymap.map(Mapping {
+            mode,
+            lhs: lhs.clone(),
+            rhs: rhs.clone(),
+            noremap: command.name.contains("noremap"),
+        });
+    }
+    Ok(vec![])
+}
+
+/**
+ * :unmap {lhs} removes a mapping
+ */
+pub fn unmap(command: &ExCommand, editor: &mut Editor) -> ExResult {
+    let lhs = match command.args.trim() {
+        "" => return Err(ExError::Invalid("Argument required".to_string())),
+        lhs => parse_keys(lhs, editor.options.mapleader.as_str()),
+    };
+    let mut found = false;
+    for &mode in modes(command) {
+        found |= editor.keymap.unmap(mode, &lhs);
+    }
+    if !found {
+        return Err(ExError::Invalid("No such mapping".to_string()));
+    }
+    Ok(vec![])
+}
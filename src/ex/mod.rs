@@ -0,0 +1,307 @@
+mod buffers;
+pub mod completion;
+mod editing;
+pub mod error;
+mod file;
+mod global;
+mod map;
+pub mod parser;
+pub mod range;
+mod set;
+pub mod substitute;
+mod tabs;
+mod windows;
+
+pub use error::ExError;
+pub use parser::ExCommand;
+
+use crate::buffer::Position;
+use crate::cmdline_window;
+use crate::config;
+use crate::editor::Editor;
+use crate::search::{Direction, Search};
+use crate::state::{State, SubstituteState};
+use crate::text::TextBuffer;
+use crate::window::SplitDirection;
+use substitute::Substitute;
+
+/**
+ * The lines an ex command applies to. Inclusive and 0-indexed.
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl LineRange {
+    pub fn new(start: usize, end: usize) -> Self {
+        LineRange { start, end }
+    }
+}
+
+/**
+ * The index of the last line, not counting the empty line after
+ * a trailing newline.
+ */
+pub fn last_line<T: TextBuffer>(text: &T) -> usize {
+    let content = text.to_string();
+    let count = content.matches('\n').count();
+    if content.ends_with('\n') && count > 0 {
+        count - 1
+    } else {
+        count
+    }
+}
+
+/**
+ * Commands return any states they push
+ */
+pub type ExResult = Result<Vec<State>, ExError>;
+
+/**
+ * Parse and run a command line
+ */
+pub fn run(text: &str, editor: &mut Editor) -> ExResult {
+    let command = ExCommand::parse(text)?;
+    execute(&command, editor)
+}
+
+pub fn execute(command: &ExCommand, editor: &mut Editor) -> ExResult {
+    let args = command.args.split_whitespace().collect::<Vec<&str>>();
+
+    match command.name.as_str() {
+        // A bare range jumps to its last line
+        "" => {
+            if command.range.is_some() {
+                let range = line_range(command, editor)?;
+                editor.text_buffer.move_cursor(Position::new(range.end, 0));
+            }
+        }
+        // In the command-line window, :q only closes the window
+        "quit" if editor.cmdline_window.is_some() => {
+            cmdline_window::close(editor);
+        }
+        "quit" => return file::quit(command, editor),
+        "write" => return file::write(command, editor),
+        "wq" => return file::write_quit(command, editor),
+        "xit" | "exit" => return file::exit(command, editor),
+        "edit" => return file::edit(command, editor),
+        "split" => return windows::split(command, editor, SplitDirection::Horizontal),
+        "vsplit" => return windows::split(command, editor, SplitDirection::Vertical),
+        "close" => return windows::close(editor),
+        "only" => return windows::only(editor),
+        "tabnew" => return tabs::new(command, editor),
+        "tabnext" => return tabs::next(command, editor),
+        "tabprevious" => return tabs::previous(editor),
+        "tabclose" => return tabs::close(editor),
+        "buffer" => return buffers::buffer(command, editor),
+        "bnext" => return buffers::cycle(command, editor, true),
+        "bprevious" => return buffers::cycle(command, editor, false),
+        "bdelete" => return buffers::delete(command, editor),
+        "ls" | "buffers" => return buffers::list(editor),
+        "nohlsearch" => editor.search_highlight = false,
+        "messages" => messages(editor),
+        "set" => return set::set(command, editor, false),
+        "setlocal" => return set::set(command, editor, true),
+        "source" => match args.as_slice() {
+            [path] => config::source(path, editor)?,
+            _ => return Err(ExError::Invalid("Argument required".to_string())),
+        },
+        "map" | "nmap" | "vmap" | "imap" | "noremap" | "nnoremap" | "vnoremap" | "inoremap" => {
+            return map::map(command, editor)
+        }
+        "unmap" | "nunmap" | "vunmap" | "iunmap" => return map::unmap(command, editor),
+        "colorscheme" => colorscheme(command, editor)?,
+        "highlight" => highlight(command, editor)?,
+        "substitute" => return substitute(command, editor),
+        "delete" => return editing::delete(command, editor),
+        "yank" => return editing::yank(command, editor),
+        "move" => return editing::move_lines(command, editor),
+        "copy" | "t" => return editing::copy_lines(command, editor),
+        "join" => return editing::join(command, editor),
+        "normal" => return editing::normal(command, editor),
+        "global" | "vglobal" => return global::global(command, editor),
+        name if name.starts_with('>') || name.starts_with('<') => {
+            return editing::shift(command, editor)
+        }
+        name => return Err(ExError::NotACommand(name.to_string())),
+    };
+    Ok(vec![])
+}
+
+/**
+ * Commands that switch buffers or windows can't run from the q: window
+ */
+fn check_cmdline_window(editor: &Editor) -> Result<(), ExError> {
+    match editor.cmdline_window {
+        Some(_) => Err(ExError::CmdlineWindow),
+        None => Ok(()),
+    }
+}
+
+/**
+ * The lines the command's range covers, defaulting to the cursor line
+ */
+pub fn line_range(command: &ExCommand, editor: &Editor) -> Result<LineRange, String> {
+    match &command.range {
+        Some(range) => range.resolve(editor),
+        None => {
+            let line = editor.text_buffer.cursor().line;
+            Ok(LineRange::new(line, line))
+        }
+    }
+}
+
+fn substitute(command: &ExCommand, editor: &mut Editor) -> ExResult {
+    let range = line_range(command, editor)?;
+    let last_pattern = editor.search.as_ref().map(|search| search.pattern.as_str());
+    let substitute = Substitute::parse(command.args.as_str(), last_pattern, &editor.options)?;
+    let pattern = substitute.pattern.clone();
+
+    // The pattern becomes the last search, so n/N and hlsearch pick it up
+    editor.search = Some(Search {
+        pattern: substitute.pattern.clone(),
+        regex: substitute.regex.clone(),
+        direction: Direction::Forward,
+    });
+    editor.search_highlight = true;
+
+    if substitute.flags.confirm {
+        return match SubstituteState::start(substitute, range, editor) {
+            Some(state) => Ok(vec![State::Substitute(state)]),
+            None => Err(ExError::PatternNotFound(pattern)),
+        };
+    }
+
+    let text = editor.text_buffer.text_buffer.as_mut();
+    let group = text.start_undo_group();
+    let last_changed = substitute::substitute_lines(text, range, &substitute);
+    text.end_undo_group(group);
+
+    match last_changed {
+        Some(line) => {
+            editor.text_buffer.move_cursor(Position::new(line, 0));
+            Ok(vec![])
+        }
+        None => Err(ExError::PatternNotFound(pattern)),
+    }
+}
+
+/**
+ * Load a color scheme, or show the current one's name
+ */
+fn colorscheme(command: &ExCommand, editor: &mut Editor) -> Result<(), ExError> {
+    match command.args.trim() {
+        "" => {
+            let name = editor.colorscheme.name.clone();
+            editor.messages.info(name.as_str());
+        }
+        name => editor.colorscheme.load(name)?,
+    }
+    Ok(())
+}
+
+/**
+ * :hi lists every group, :hi Group shows one and :hi Group fg=.. changes it
+ */
+fn highlight(command: &ExCommand, editor: &mut Editor) -> Result<(), ExError> {
+    let args = command.args.split_whitespace().collect::<Vec<&str>>();
+    match args.as_slice() {
+        [] => {
+            let listing = editor.colorscheme.listing();
+            editor.messages.show_only(listing.as_str());
+        }
+        [group] => {
+            let text = format!("{} {}", group, editor.colorscheme.get(group));
+            editor.messages.info(text.as_str());
+        }
+        _ => editor.colorscheme.highlight(command.args.as_str())?,
+    }
+    Ok(())
+}
+
+/**
+ * Show every message so far, one per line
+ */
+fn messages(editor: &mut Editor) {
+    let text = editor
+        .messages
+        .history()
+        .iter()
+        .map(|message| message.text.as_str())
+        .collect::<Vec<&str>>()
+        .join("\n");
+    if !text.is_empty() {
+        editor.messages.show_only(text.as_str());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::event::Event;
+    use crate::message::MessageKind;
+    use termion::event::Key;
+
+    #[test]
+    fn test_errors() {
+        let mut editor = Editor::new();
+        match run("quux", &mut editor) {
+            Err(ExError::NotACommand(name)) => assert_eq!(name, "quux"),
+            _ => panic!("expected an unknown command error"),
+        }
+
+        // Errors from the command line end up in the message area
+        for c in ":s/x/y/\n".chars() {
+            editor.handle_event(Event::Key(Key::Char(c)));
+        }
+        let message = editor.messages.current.clone().unwrap();
+        assert_eq!(message.kind, MessageKind::Error);
+        assert_eq!(message.text, "Pattern not found: x");
+
+        run("messages", &mut editor).unwrap();
+        assert_eq!(editor.messages.history().len(), 1);
+        assert!(editor.messages.current.is_some());
+    }
+
+    #[test]
+    fn test_quit_modified() {
+        let mut editor = Editor::new();
+        run("quit", &mut editor).unwrap();
+        assert!(!editor.running);
+
+        let mut editor = Editor::new();
+        editor.text_buffer.insert('a');
+        assert!(editor.is_modified());
+        match run("q", &mut editor) {
+            Err(ExError::NoWriteSinceChange) => assert!(editor.running),
+            _ => panic!("expected :q to refuse"),
+        }
+        run("q!", &mut editor).unwrap();
+        assert!(!editor.running);
+    }
+
+    #[test]
+    fn test_write_quit_split() {
+        let path = std::env::temp_dir().join(format!("vik-wq-{}", std::process::id()));
+        let mut editor = Editor::new();
+        editor.filename = Some(path.to_str().unwrap().to_string());
+        editor.text_buffer.insert('a');
+        run("split", &mut editor).unwrap();
+
+        // With a split open, :wq and :x only close the window
+        run("wq", &mut editor).unwrap();
+        assert!(editor.running);
+        assert_eq!(editor.windows.count(), 1);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "a");
+
+        run("split", &mut editor).unwrap();
+        run("x", &mut editor).unwrap();
+        assert!(editor.running);
+        assert_eq!(editor.windows.count(), 1);
+        run("x", &mut editor).unwrap();
+        assert!(!editor.running);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,147 @@
+use super::error::ExError;
+use super::range::Range;
+
+/**
+ * Full command names and the shortest abbreviation accepted for each
+ */
+pub const COMMANDS: &[(&str, &str)] = &[
+    ("bdelete", "bd"),
+    ("bnext", "bn"),
+    ("bprevious", "bp"),
+    ("buffer", "b"),
+    ("buffers", "buffers"),
+    ("close", "clo"),
+    ("colorscheme", "colo"),
+    ("copy", "co"),
+    ("delete", "d"),
+    ("edit", "e"),
+    ("exit", "exi"),
+    ("global", "g"),
+    ("highlight", "hi"),
+    ("imap", "im"),
+    ("inoremap", "ino"),
+    ("iunmap", "iu"),
+    ("join", "j"),
+    ("ls", "ls"),
+    ("map", "map"),
+    ("messages", "mes"),
+    ("move", "m"),
+    ("nmap", "nm"),
+    ("nnoremap", "nn"),
+    ("nohlsearch", "noh"),
+    ("noremap", "no"),
+    ("normal", "norm"),
+    ("nunmap", "nun"),
+    ("only", "on"),
+    ("quit", "q"),
+    ("set", "se"),
+    ("setlocal", "setl"),
+    ("source", "so"),
+    ("split", "sp"),
+    ("substitute", "s"),
+    ("t", "t"),
+    ("tabclose", "tabc"),
+    ("tabnew", "tabnew"),
+    ("tabnext", "tabn"),
+    ("tabprevious", "tabp"),
+    ("unmap", "unm"),
+    ("vglobal", "v"),
+    ("vmap", "vm"),
+    ("vnoremap", "vn"),
+    ("vsplit", "vs"),
+    ("vunmap", "vu"),
+    ("wq", "wq"),
+    ("write", "w"),
+    ("xit", "x"),
+    ("yank", "y"),
+];
+
+/**
+ * A parsed command line: `[range]name[!] [args]`
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExCommand {
+    pub range: Option<Range>,
+    pub name: String,
+    pub bang: bool,
+    pub args: String,
+}
+
+impl ExCommand {
+    pub fn parse(text: &str) -> Result<Self, ExError> {
+        let text = text.trim_start_matches(|c: char| c == ':' || c.is_whitespace());
+        let (range, rest) = match Range::parse(text) {
+            Some((range, rest)) => (Some(range), rest.trim_start()),
+            None => (None, text),
+        };
+
+        // Names are either alphabetic or a run of a single symbol, like :>>
+        let name_len = match rest.chars().next() {
+            Some(c) if c.is_ascii_alphabetic() => {
+                rest.chars().take_while(|c| c.is_ascii_alphabetic()).count()
+            }
+            Some(c) if "<>&!=".contains(c) => rest.chars().take_while(|&d| d == c).count(),
+            Some(_) => return Err(ExError::NotACommand(rest.to_string())),
+            None => 0,
+        };
+        let (name, rest) = rest.split_at(name_len);
+
+        // Bang must directly follow the name
+        let (bang, rest) = match rest.strip_prefix('!') {
+            Some(rest) if !name.is_empty() && name != "!" => (true, rest),
+            _ => (false, rest),
+        };
+
+        Ok(ExCommand {
+            range,
+            name: resolve_name(name),
+            bang,
+            args: rest.trim_start().to_string(),
+        })
+    }
+}
+
+/**
+ * Expand an abbreviation to the full command name. Unknown names are
+ * returned as-is.
+ */
+pub fn resolve_name(name: &str) -> String {
+    COMMANDS
+        .iter()
+        .find(|(full, abbreviation)| full.starts_with(name) && name.starts_with(abbreviation))
+        .map_or(name, |(full, _)| full)
+        .to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::range::{Address, LineSpec};
+    use super::*;
+
+    #[test]
+    fn test_parse_command() {
+        let command = ExCommand::parse("w! foo.txt").unwrap();
+        assert_eq!(command.name, "write");
+        assert!(command.bang);
+        assert_eq!(command.args, "foo.txt");
+
+        let command = ExCommand::parse(":%s/a/b/g").unwrap();
+        assert_eq!(command.range, Some(Range::Entire));
+        assert_eq!(command.name, "substitute");
+        assert_eq!(command.args, "/a/b/g");
+
+        let command = ExCommand::parse("12").unwrap();
+        assert_eq!(
+            command.range,
+            Some(Range::Single(Address::new(LineSpec::Absolute(12))))
+        );
+        assert_eq!(command.name, "");
+
+        let command = ExCommand::parse(">>").unwrap();
+        assert_eq!(command.name, ">>");
+
+        assert_eq!(ExCommand::parse("noh").unwrap().name, "nohlsearch");
+        assert_eq!(ExCommand::parse("subs/a/b/").unwrap().name, "substitute");
+        assert_eq!(ExCommand::parse("quux").unwrap().name, "quux");
+    }
+}
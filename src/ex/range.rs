@@ -0,0 +1,336 @@
+use super::LineRange;
+use crate::editor::Editor;
+use crate::search::Direction;
+use crate::text::TextBuffer;
+use regex::{Regex, RegexBuilder};
+
+/**
+ * The line an address starts from, before any offsets
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum LineSpec {
+    // A 1-indexed line number. 0 is the position before the first line.
+    Absolute(usize),
+    Current,
+    Last,
+    Mark(char),
+    // The next line matching the pattern. An empty pattern reuses the last search.
+    Search(String, Direction),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Address {
+    pub line: LineSpec,
+    pub offset: isize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Range {
+    // %
+    Entire,
+    Single(Address),
+    // With `;` the cursor moves to the first address before the second is resolved
+    Pair(Address, Address, Separator),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Separator {
+    Comma,
+    Semicolon,
+}
+
+impl Address {
+    pub fn new(line: LineSpec) -> Self {
+        Address { line, offset: 0 }
+    }
+
+    /**
+     * Parse an address like `.`, `$`, `12`, `'a`, `/pat/` or `+2`, with any
+     * number of trailing `+N`/`-N` offsets.
+     */
+    pub fn parse(text: &str) -> Option<(Address, &str)> {
+        let (line, mut rest) = match text.chars().next()? {
+            '.' => (LineSpec::Current, &text[1..]),
+            '$' => (LineSpec::Last, &text[1..]),
+            '\'' => {
+                let mark = text[1..].chars().next()?;
+                (LineSpec::Mark(mark), &text[1 + mark.len_utf8()..])
+            }
+            delimiter @ '/' | delimiter @ '?' => {
+                let (pattern, rest) = parse_pattern(&text[1..], delimiter);
+                let direction = if delimiter == '/' {
+                    Direction::Forward
+                } else {
+                    Direction::Backward
+                };
+                (LineSpec::Search(pattern, direction), rest)
+            }
+            '+' | '-' => (LineSpec::Current, text),
+            c if c.is_ascii_digit() => {
+                let (number, rest) = parse_number(text);
+                (LineSpec::Absolute(number), rest)
+            }
+            _ => return None,
+        };
+
+        let mut address = Address::new(line);
+        loop {
+            let sign = match rest.chars().next() {
+                Some('+') => 1,
+                Some('-') => -1,
+                _ => break,
+            };
+            rest = &rest[1..];
+            let count = if rest.starts_with(|c: char| c.is_ascii_digit()) {
+                let (number, remaining) = parse_number(rest);
+                rest = remaining;
+                number
+            } else {
+                1
+            };
+            address.offset += sign * count as isize;
+        }
+
+        Some((address, rest))
+    }
+
+    /**
+     * Resolve to a 1-indexed line number, where 0 is before the first line
+     */
+    pub fn resolve(&self, editor: &Editor, current: usize) -> Result<usize, String> {
+        let text = editor.text_buffer.text_buffer.as_ref();
+        let last = super::last_line(text) + 1;
+
+        let line = match &self.line {
+            LineSpec::Absolute(line) => *line,
+            LineSpec::Current => current,
+            LineSpec::Last => last,
+            LineSpec::Mark(mark) => {
+                let pos = editor
+                    .text_buffer
+                    .marks
+                    .get(*mark)
+                    .ok_or_else(|| format!("Mark not set: {}", mark))?;
+                pos.line + 1
+            }
+            LineSpec::Search(pattern, direction) => {
+                let regex = if pattern.is_empty() {
+                    editor
+                        .search
+                        .as_ref()
+                        .map(|search| search.regex.clone())
+                        .ok_or("No previous regular expression")?
+                } else {
+                    RegexBuilder::new(pattern.as_str())
+                        .case_insensitive(editor.options.ignore_case(pattern))
+                        .build()
+                        .map_err(|e| e.to_string())?
+                };
+                search_line(text, &regex, current, *direction)
+                    .ok_or_else(|| format!("Pattern not found: {}", pattern))?
+            }
+        };
+
+        let line = line as isize + self.offset;
+        if line < 0 || line as usize > last {
+            return Err("Invalid range".to_string());
+        }
+        Ok(line as usize)
+    }
+}
+
+impl Range {
+    pub fn parse(text: &str) -> Option<(Range, &str)> {
+        if let Some(rest) = text.strip_prefix('%') {
+            return Some((Range::Entire, rest));
+        }
+
+        // A missing address next to a separator is the current line
+        let (start, rest) = match Address::parse(text) {
+            Some(parsed) => parsed,
+            None if text.starts_with([',', ';']) => (Address::new(LineSpec::Current), text),
+            None => return None,
+        };
+
+        let separator = match rest.chars().next() {
+            Some(',') => Separator::Comma,
+            Some(';') => Separator::Semicolon,
+            _ => return Some((Range::Single(start), rest)),
+        };
+        let rest = &rest[1..];
+        let (end, rest) = Address::parse(rest).unwrap_or((Address::new(LineSpec::Current), rest));
+
+        Some((Range::Pair(start, end, separator), rest))
+    }
+
+    /**
+     * Resolve the lines the range covers. A backwards range is swapped.
+     */
+    pub fn resolve(&self, editor: &Editor) -> Result<LineRange, String> {
+        let current = editor.text_buffer.cursor().line + 1;
+        let (start, end) = match self {
+            Range::Entire => {
+                let text = editor.text_buffer.text_buffer.as_ref();
+                return Ok(LineRange::new(0, super::last_line(text)));
+            }
+            Range::Single(address) => {
+                let line = address.resolve(editor, current)?;
+                (line, line)
+            }
+            Range::Pair(start, end, separator) => {
+                let start = start.resolve(editor, current)?;
+                let current = match separator {
+                    Separator::Comma => current,
+                    Separator::Semicolon => start,
+                };
+                (start, end.resolve(editor, current)?)
+            }
+        };
+
+        let (start, end) = if start > end {
+            (end, start)
+        } else {
+            (start, end)
+        };
+        Ok(LineRange::new(
+            start.saturating_sub(1),
+            end.saturating_sub(1),
+        ))
+    }
+}
+
+fn parse_number(text: &str) -> (usize, &str) {
+    let digits = text.chars().take_while(|c| c.is_ascii_digit()).count();
+    (text[..digits].parse().unwrap_or(0), &text[digits..])
+}
+
+/**
+ * Read a pattern up to an unescaped delimiter. The closing delimiter
+ * is optional at the end of the text.
+ */
+pub fn parse_pattern(text: &str, delimiter: char) -> (String, &str) {
+    let mut pattern = String::new();
+    let mut chars = text.char_indices();
+    while let Some((idx, c)) = chars.next() {
+        if c == delimiter {
+            return (pattern, &text[idx + c.len_utf8()..]);
+        }
+        if c == '\\' {
+            match chars.next() {
+                Some((_, next)) if next == delimiter => pattern.push(next),
+                Some((_, next)) => {
+                    pattern.push('\\');
+                    pattern.push(next);
+                }
+                None => pattern.push('\\'),
+            }
+            continue;
+        }
+        pattern.push(c);
+    }
+    (pattern, "")
+}
+
+/**
+ * Find the next 1-indexed line after (or before) current that matches,
+ * wrapping around the text.
+ */
+fn search_line<T: TextBuffer>(
+    text: &T,
+    regex: &Regex,
+    current: usize,
+    direction: Direction,
+) -> Option<usize> {
+    let content = text.to_string();
+    let lines = content.lines().collect::<Vec<&str>>();
+    let count = lines.len();
+    if count == 0 {
+        return None;
+    }
+
+    // current is 1-indexed, so current % count is the next line's index
+    let current = current.min(count);
+    (0..count)
+        .map(|i| match direction {
+            Direction::Forward => (current + i) % count,
+            Direction::Backward => (current + 2 * count - 2 - i) % count,
+        })
+        .find(|&idx| regex.is_match(lines[idx]))
+        .map(|idx| idx + 1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn address(line: LineSpec, offset: isize) -> Address {
+        Address { line, offset }
+    }
+
+    #[test]
+    fn test_parse_address() {
+        assert_eq!(
+            Address::parse("12d"),
+            Some((address(LineSpec::Absolute(12), 0), "d"))
+        );
+        assert_eq!(
+            Address::parse(".+3-1"),
+            Some((address(LineSpec::Current, 2), ""))
+        );
+        assert_eq!(
+            Address::parse("++"),
+            Some((address(LineSpec::Current, 2), ""))
+        );
+        assert_eq!(
+            Address::parse("'a,"),
+            Some((address(LineSpec::Mark('a'), 0), ","))
+        );
+        assert_eq!(
+            Address::parse("/fo\\/o/+1s"),
+            Some((
+                address(LineSpec::Search("fo/o".to_string(), Direction::Forward), 1),
+                "s"
+            ))
+        );
+        assert_eq!(Address::parse("d"), None);
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(Range::parse("%s/a/b/"), Some((Range::Entire, "s/a/b/")));
+        assert_eq!(
+            Range::parse(".,$d"),
+            Some((
+                Range::Pair(
+                    address(LineSpec::Current, 0),
+                    address(LineSpec::Last, 0),
+                    Separator::Comma
+                ),
+                "d"
+            ))
+        );
+        assert_eq!(
+            Range::parse("'<,'>"),
+            Some((
+                Range::Pair(
+                    address(LineSpec::Mark('<'), 0),
+                    address(LineSpec::Mark('>'), 0),
+                    Separator::Comma
+                ),
+                ""
+            ))
+        );
+        assert_eq!(
+            Range::parse("3;+2y"),
+            Some((
+                Range::Pair(
+                    address(LineSpec::Absolute(3), 0),
+                    address(LineSpec::Current, 2),
+                    Separator::Semicolon
+                ),
+                "y"
+            ))
+        );
+        assert_eq!(Range::parse("w"), None);
+    }
+}
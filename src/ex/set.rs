@@ -0,0 +1,158 @@
+use super::{ExCommand, ExError, ExResult};
+use crate::editor::Editor;
+use crate::options::{find, Value, OPTIONS};
+
+/**
+ * :set and :setlocal. Each argument sets, toggles, resets or shows an
+ * option: `opt`, `noopt`, `opt!`, `invopt`, `opt&`, `opt?`, `opt=value`
+ * and, for numbers, `opt+=n` and `opt-=n`. Without arguments, lists the
+ * options that differ from their defaults.
+ */
+pub fn set(command: &ExCommand, editor: &mut Editor, local_only: bool) -> ExResult {
+    let args = command.args.split_whitespace().collect::<Vec<&str>>();
+    if args.is_empty() {
+        let changed = OPTIONS
+            .iter()
+            .filter_map(|(name, _, _)| {
+                let value = editor.option(name)?;
+                let changed = editor.default_option(name).as_ref() != Some(&value);
+                changed.then(|| describe(name, &value))
+            })
+            .collect::<Vec<String>>();
+        let listing = std::iter::once("--- Options ---".to_string())
+            .chain(changed)
+            .collect::<Vec<String>>()
+            .join("\n");
+        editor.messages.show_only(listing.as_str());
+        return Ok(vec![]);
+    }
+
+    let mut shown = vec![];
+    for arg in args {
+        if let Some(text) = set_arg(arg, editor, local_only)? {
+            shown.push(text);
+        }
+    }
+    if !shown.is_empty() {
+        editor.messages.info(shown.join("  ").as_str());
+    }
+    Ok(vec![])
+}
+
+/**
+ * Apply one argument, returning the option's description if it asks for
+ * it to be shown
+ */
+fn set_arg(arg: &str, editor: &mut Editor, local_only: bool) -> Result<Option<String>, ExError> {
+    let name_len = arg
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(arg.len());
+    let (name, rest) = arg.split_at(name_len);
+    let invalid = || ExError::Invalid(format!("Invalid argument: {}", arg));
+
+    // noopt and invopt turn off and toggle boolean options
+    let (name, current) = match editor.option(name) {
+        Some(value) => (name, value),
+        None => {
+            let (option, value) = match (name.strip_prefix("no"), name.strip_prefix("inv")) {
+                (Some(option), _) => (option, Some(false)),
+                (_, Some(option)) => (option, None),
+                _ => return Err(ExError::UnknownOption(name.to_string())),
+            };
+            let current = match editor.option(option) {
+                Some(Value::Bool(current)) if rest.is_empty() => current,
+                Some(_) => return Err(invalid()),
+                None => return Err(ExError::UnknownOption(name.to_string())),
+            };
+            let value = Value::Bool(value.unwrap_or(!current));
+            editor.set_option(option, &value, local_only)?;
+            return Ok(None);
+        }
+    };
+
+    let value = match (rest, &current) {
+        ("?", _) | ("", Value::Number(_)) | ("", Value::String(_)) => {
+            let (name, _) = find(name).ok_or_else(invalid)?;
+            return Ok(Some(describe(name, &current)));
+        }
+        ("", Value::Bool(_)) => Value::Bool(true),
+        ("!", Value::Bool(value)) => Value::Bool(!value),
+        ("&", _) => editor.default_option(name).ok_or_else(invalid)?,
+        _ => {
+            let (op, text) = match rest.find(&['=', ':'][..]) {
+                Some(idx) => (&rest[..idx], &rest[idx + 1..]),
+                None => return Err(invalid()),
+            };
+            let value = current.parse_like(text).ok_or_else(invalid)?;
+            match (op, &current, value) {
+                ("", _, value) => value,
+                ("+", Value::Number(a), Value::Number(b)) => Value::Number(a + b),
+                ("-", Value::Number(a), Value::Number(b)) => Value::Number(a.saturating_sub(b)),
+                _ => return Err(invalid()),
+            }
+        }
+    };
+    editor.set_option(name, &value, local_only)?;
+    Ok(None)
+}
+
+/**
+ * How :set shows an option: `opt` or `noopt` for booleans, otherwise
+ * `opt=value`
+ */
+fn describe(name: &str, value: &Value) -> String {
+    match value {
+        Value::Bool(true) => name.to_string(),
+        Value::Bool(false) => format!("no{}", name),
+        value => format!("{}={}", name, value),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ex::run;
+
+    #[test]
+    fn test_set() {
+        let mut editor = Editor::new();
+        run("set ts=4 nohls et! sw+=2", &mut editor).unwrap();
+        assert_eq!(editor.text_buffer.options.tabstop, 4);
+        assert_eq!(editor.options.buffer.tabstop, 4);
+        assert!(!editor.options.hlsearch);
+        assert!(editor.text_buffer.options.expandtab);
+        assert_eq!(editor.text_buffer.options.shiftwidth, 6);
+
+        // :setlocal leaves the global value for new buffers alone
+        run("setlocal nowrap ts&", &mut editor).unwrap();
+        assert!(!editor.text_window_state.options.wrap);
+        assert!(editor.options.window.wrap);
+        assert_eq!(editor.text_buffer.options.tabstop, 8);
+        assert_eq!(editor.options.buffer.tabstop, 4);
+
+        run("set ts? invnumber ff", &mut editor).unwrap();
+        let message = editor.messages.current.as_ref().unwrap();
+        assert_eq!(message.text, "tabstop=8  fileformat=unix");
+        assert!(editor.text_window_state.options.number);
+
+        assert!(run("set ts=0", &mut editor).is_err());
+        assert!(run("set hls=1", &mut editor).is_err());
+        assert!(run("set nots", &mut editor).is_err());
+        assert!(run("set quux", &mut editor).is_err());
+    }
+
+    #[test]
+    fn test_new_window_options() {
+        let mut editor = Editor::new();
+        run("set number", &mut editor).unwrap();
+        run("setlocal nowrap", &mut editor).unwrap();
+
+        // New windows and tab pages start from the global values
+        for command in &["split", "tabnew"] {
+            run(command, &mut editor).unwrap();
+            assert!(editor.text_window_state.options.number);
+            assert!(editor.text_window_state.options.wrap);
+            run("setlocal nowrap", &mut editor).unwrap();
+        }
+    }
+}
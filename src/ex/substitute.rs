@@ -0,0 +1,312 @@
+use super::LineRange;
+use crate::buffer::Position;
+use crate::options::Options;
+use crate::text::{PieceTableBuffer, Range, TextBuffer};
+use regex::{Captures, Regex, RegexBuilder};
+
+#[derive(Debug, Clone, Default)]
+pub struct Flags {
+    pub global: bool,
+    pub confirm: bool,
+    pub ignore_case: Option<bool>,
+}
+
+/**
+ * A parsed :s/pattern/replacement/flags command
+ */
+#[derive(Debug, Clone)]
+pub struct Substitute {
+    pub pattern: String,
+    pub regex: Regex,
+    pub replacement: String,
+    pub flags: Flags,
+}
+
+/**
+ * A match within a single line, with the replacement already expanded
+ */
+#[derive(Debug, Clone)]
+pub struct Match {
+    pub start: usize,
+    pub end: usize,
+    pub replacement: String,
+}
+
+/**
+ * Split on the delimiter, unescaping any escaped delimiters. Other escapes
+ * are left in place for the regex or the replacement.
+ */
+fn split_delimited(text: &str, delimiter: char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(next) if next == delimiter => parts.last_mut().unwrap().push(next),
+                Some(next) => {
+                    let part = parts.last_mut().unwrap();
+                    part.push('\\');
+                    part.push(next);
+                }
+                None => parts.last_mut().unwrap().push('\\'),
+            },
+            c if c == delimiter && parts.len() < 3 => parts.push(String::new()),
+            c => parts.last_mut().unwrap().push(c),
+        }
+    }
+    parts
+}
+
+impl Substitute {
+    /**
+     * Parse the arguments of a substitute, starting from the delimiter.
+     * An empty pattern reuses the last search pattern.
+     */
+    pub fn parse(
+        args: &str,
+        last_pattern: Option<&str>,
+        options: &Options,
+    ) -> Result<Self, String> {
+        let delimiter = args.chars().next().ok_or("Missing pattern")?;
+        let parts = split_delimited(&args[delimiter.len_utf8()..], delimiter);
+
+        let pattern = match parts[0].as_str() {
+            "" => last_pattern.ok_or("No previous regular expression")?,
+            pattern => pattern,
+        }
+        .to_string();
+        let replacement = parts.get(1).cloned().unwrap_or_default();
+
+        let mut flags = Flags::default();
+        for flag in parts.get(2).map_or("", |f| f.as_str()).trim().chars() {
+            match flag {
+                'g' => flags.global = true,
+                'c' => flags.confirm = true,
+                'i' => flags.ignore_case = Some(true),
+                'I' => flags.ignore_case = Some(false),
+                _ => return Err(format!("Invalid flag: {}", flag)),
+            }
+        }
+
+        let regex = RegexBuilder::new(pattern.as_str())
+            .case_insensitive(
+                flags
+                    .ignore_case
+                    .unwrap_or_else(|| options.ignore_case(pattern.as_str())),
+            )
+            .build()
+            .map_err(|e| e.to_string())?;
+
+        Ok(Substitute {
+            pattern,
+            regex,
+            replacement,
+            flags,
+        })
+    }
+
+    /**
+     * The matches in a line that should be replaced. Without the `g` flag,
+     * only the first.
+     */
+    pub fn line_matches(&self, line: &str) -> Vec<Match> {
+        let matches = self.regex.captures_iter(line).map(|caps| {
+            let m = caps.get(0).unwrap();
+            Match {
+                start: m.start(),
+                end: m.end(),
+                replacement: self.expand(&caps),
+            }
+        });
+
+        if self.flags.global {
+            matches.collect()
+        } else {
+            matches.take(1).collect()
+        }
+    }
+
+    /**
+     * Find the next match at or after the column, searching forward through
+     * the lines up to end.
+     */
+    pub fn next_match(
+        &self,
+        text: &PieceTableBuffer,
+        line: usize,
+        col: usize,
+        end: usize,
+    ) -> Option<(usize, Match)> {
+        (line..=end).find_map(|lineno| {
+            let min_col = if lineno == line { col } else { 0 };
+            self.regex
+                .captures_iter(text.line(lineno).as_str())
+                .find(|caps| caps.get(0).unwrap().start() >= min_col)
+                .map(|caps| {
+                    let m = caps.get(0).unwrap();
+                    let found = Match {
+                        start: m.start(),
+                        end: m.end(),
+                        replacement: self.expand(&caps),
+                    };
+                    (lineno, found)
+                })
+        })
+    }
+
+    /**
+     * Expand the replacement for a match. Supports `&` and `\0`-`\9` for
+     * captures, `\u`/`\l` to change the case of the next character,
+     * `\U`/`\L` until `\E`, and `\r`/`\n` for a line break.
+     */
+    pub fn expand(&self, caps: &Captures) -> String {
+        let mut expander = CaseExpander::new();
+        let mut chars = self.replacement.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '&' => expander.push_str(caps.get(0).map_or("", |m| m.as_str())),
+                '\\' => match chars.next() {
+                    Some(d) if d.is_ascii_digit() => {
+                        let group = d.to_digit(10).unwrap() as usize;
+                        expander.push_str(caps.get(group).map_or("", |m| m.as_str()));
+                    }
+                    Some('u') => expander.next_case = Some(Case::Upper),
+                    Some('l') => expander.next_case = Some(Case::Lower),
+                    Some('U') => expander.case = Some(Case::Upper),
+                    Some('L') => expander.case = Some(Case::Lower),
+                    Some('E') | Some('e') => expander.case = None,
+                    Some('r') | Some('n') => expander.output.push('\n'),
+                    Some('t') => expander.output.push('\t'),
+                    Some(other) => expander.push(other),
+                    None => expander.push('\\'),
+                },
+                c => expander.push(c),
+            }
+        }
+        expander.output
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Case {
+    Upper,
+    Lower,
+}
+
+struct CaseExpander {
+    output: String,
+    case: Option<Case>,
+    next_case: Option<Case>,
+}
+
+impl CaseExpander {
+    fn new() -> Self {
+        CaseExpander {
+            output: String::new(),
+            case: None,
+            next_case: None,
+        }
+    }
+
+    fn push(&mut self, c: char) {
+        match self.next_case.take().or(self.case) {
+            Some(Case::Upper) => self.output.extend(c.to_uppercase()),
+            Some(Case::Lower) => self.output.extend(c.to_lowercase()),
+            None => self.output.push(c),
+        }
+    }
+
+    fn push_str(&mut self, s: &str) {
+        s.chars().for_each(|c| self.push(c))
+    }
+}
+
+/**
+ * Replace a match in the line with its replacement
+ */
+pub fn replace_match(text: &mut PieceTableBuffer, line: usize, m: &Match) {
+    let start = Position::new(line, m.start);
+    if m.end > m.start {
+        text.delete(Range::new(start, m.end - m.start));
+    }
+    if !m.replacement.is_empty() {
+        text.insert_str(start, m.replacement.as_str());
+    }
+}
+
+/**
+ * Run the substitution over every line in the range. Returns the last line
+ * that was changed, if any.
+ */
+pub fn substitute_lines(
+    text: &mut PieceTableBuffer,
+    range: LineRange,
+    substitute: &Substitute,
+) -> Option<usize> {
+    let mut line = range.start;
+    let mut end = range.end;
+    let mut last_changed = None;
+
+    while line <= end {
+        let matches = substitute.line_matches(text.line(line).as_str());
+
+        // Work right to left so earlier offsets stay valid
+        for m in matches.iter().rev() {
+            replace_match(text, line, m);
+        }
+
+        // Replacements can split the line
+        let added = matches
+            .iter()
+            .map(|m| m.replacement.matches('\n').count())
+            .sum::<usize>();
+        if !matches.is_empty() {
+            last_changed = Some(line + added);
+        }
+        line += added + 1;
+        end += added;
+    }
+
+    last_changed
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn run(text: &str, args: &str, range: LineRange) -> String {
+        let mut table = PieceTableBuffer::new(text.to_string());
+        let substitute = Substitute::parse(args, None, &Options::new()).unwrap();
+        substitute_lines(&mut table, range, &substitute);
+        table.to_string()
+    }
+
+    #[test]
+    fn test_substitute_flags() {
+        let text = "foo foo\nfoo\nbar\n";
+        assert_eq!(
+            run(text, "/foo/x/", LineRange::new(0, 2)),
+            "x foo\nx\nbar\n"
+        );
+        assert_eq!(
+            run(text, "/foo/x/g", LineRange::new(0, 0)),
+            "x x\nfoo\nbar\n"
+        );
+        assert_eq!(
+            run(text, "/FOO/x/gi", LineRange::new(1, 2)),
+            "foo foo\nx\nbar\n"
+        );
+    }
+
+    #[test]
+    fn test_substitute_expand() {
+        let text = "hello world\n";
+        let range = LineRange::new(0, 0);
+        assert_eq!(run(text, r"/(\w+) (\w+)/\2 \1/", range), "world hello\n");
+        assert_eq!(run(text, r"/\w+/[&]/g", range), "[hello] [world]\n");
+        assert_eq!(run(text, r"/\w+/\u&/g", range), "Hello World\n");
+        assert_eq!(run(text, r"/(\w+) /\U\1\E-/", range), "HELLO-world\n");
+        assert_eq!(run(text, r"/ /\r/", range), "hello\nworld\n");
+        assert_eq!(run(text, r"#o#\##g", range), "hell# w#rld\n");
+    }
+}
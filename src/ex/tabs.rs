@@ -0,0 +1,47 @@
+use super::{check_cmdline_window, ExCommand, ExResult};
+use crate::editor::Editor;
+
+/**
+ * :tabnew, editing a file or a new empty buffer in the new tab page
+ */
+pub fn new(command: &ExCommand, editor: &mut Editor) -> ExResult {
+    check_cmdline_window(editor)?;
+    editor.new_tab();
+
+    let filename = command.args.trim();
+    if filename.is_empty() {
+        editor.new_buffer();
+    } else {
+        editor.open_file(filename)?;
+    }
+    Ok(vec![])
+}
+
+/**
+ * :tabn goes to the next tab page, or with a number to that one
+ */
+pub fn next(command: &ExCommand, editor: &mut Editor) -> ExResult {
+    check_cmdline_window(editor)?;
+    match command.args.trim() {
+        "" => editor.cycle_tab(1)?,
+        n => {
+            let n = n
+                .parse::<usize>()
+                .map_err(|_| format!("Invalid argument: {}", n))?;
+            editor.switch_tab(n.saturating_sub(1))?;
+        }
+    }
+    Ok(vec![])
+}
+
+pub fn previous(editor: &mut Editor) -> ExResult {
+    check_cmdline_window(editor)?;
+    editor.cycle_tab(-1)?;
+    Ok(vec![])
+}
+
+pub fn close(editor: &mut Editor) -> ExResult {
+    check_cmdline_window(editor)?;
+    editor.close_tab()?;
+    Ok(vec![])
+}
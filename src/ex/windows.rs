@@ -0,0 +1,29 @@
+use super::{check_cmdline_window, ExCommand, ExResult};
+use crate::editor::Editor;
+use crate::window::SplitDirection;
+
+/**
+ * :sp and :vsp, optionally opening a file in the new window
+ */
+pub fn split(command: &ExCommand, editor: &mut Editor, direction: SplitDirection) -> ExResult {
+    check_cmdline_window(editor)?;
+    editor.split_window(direction);
+
+    let filename = command.args.trim();
+    if !filename.is_empty() {
+        editor.open_file(filename)?;
+    }
+    Ok(vec![])
+}
+
+pub fn close(editor: &mut Editor) -> ExResult {
+    check_cmdline_window(editor)?;
+    editor.close_window()?;
+    Ok(vec![])
+}
+
+pub fn only(editor: &mut Editor) -> ExResult {
+    check_cmdline_window(editor)?;
+    editor.only_window();
+    Ok(vec![])
+}
@@ -1,18 +1,284 @@
 use crate::buffer::Buffer;
+use crate::encoding::{self, FileFormat};
+use crate::options::{BackupCopy, Options};
+use crate::syntax::Highlighter;
 use crate::text::{PieceTableBuffer, TextBuffer};
 use std::error::Error;
-use std::fs;
+use std::fs::{self, File, Metadata, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::{fchown, MetadataExt};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::SystemTime;
 
-pub fn load_file(filename: &str) -> Result<Buffer<PieceTableBuffer>, Box<dyn Error + 'static>> {
-    let content = fs::read_to_string(filename)?;
+/**
+ * What we last saw of a file on disk, to notice changes made by others,
+ * and the buffer's change tick when it was last read or written. The
+ * format is what the next write uses, and can differ from the saved one.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct FileInfo {
+    pub modified: Option<SystemTime>,
+    pub new_file: bool,
+    pub readonly: bool,
+    pub saved_tick: usize,
+    pub format: FileFormat,
+    pub saved_format: FileFormat,
+}
+
+impl FileInfo {
+    pub fn read(filename: &str) -> Self {
+        let metadata = fs::metadata(filename);
+        FileInfo {
+            modified: metadata.as_ref().ok().and_then(|m| m.modified().ok()),
+            new_file: metadata.is_err(),
+            // Opening for writing without truncating doesn't touch the file
+            readonly: metadata.is_ok() && OpenOptions::new().write(true).open(filename).is_err(),
+            saved_tick: 0,
+            format: FileFormat::default(),
+            saved_format: FileFormat::default(),
+        }
+    }
 
-    Ok(Buffer::new(Box::new(PieceTableBuffer::new(content))))
+    /**
+     * Whether the file was modified since it was last read or written
+     */
+    pub fn changed_on_disk(&self, filename: &str) -> bool {
+        match (self.modified, FileInfo::read(filename).modified) {
+            (Some(before), Some(now)) => before != now,
+            _ => false,
+        }
+    }
 }
 
+/**
+ * Read a file into a new buffer. A file that doesn't exist yet gives an
+ * empty buffer.
+ */
+pub fn load_file(
+    filename: &str,
+) -> Result<(Buffer<PieceTableBuffer>, FileInfo), Box<dyn Error + 'static>> {
+    let bytes = match fs::read(filename) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => vec![],
+        Err(e) => return Err(format!("\"{}\" {}", filename, e).into()),
+    };
+    let (content, format) = encoding::decode(bytes.as_slice());
+    let info = FileInfo {
+        format,
+        saved_format: format,
+        ..FileInfo::read(filename)
+    };
+
+    let mut buffer = Buffer::new(Box::new(PieceTableBuffer::new(content)));
+    buffer.syntax = Highlighter::for_file(filename);
+    Ok((buffer, info))
+}
+
+/**
+ * Write the buffer without ever leaving a half written file behind. The
+ * text goes to a temporary file next to the original, which is synced and
+ * renamed over it, unless backupcopy says to overwrite the original in
+ * place after copying it. If the directory can't take the temporary file,
+ * the original is overwritten in place.
+ */
 pub fn write_file(
     filename: &str,
     buffer: &Buffer<PieceTableBuffer>,
-) -> Result<(), Box<dyn Error + 'static>> {
-    fs::write(filename, buffer.text_buffer.to_string().as_str())?;
+    format: FileFormat,
+    options: &Options,
+) -> Result<FileInfo, Box<dyn Error + 'static>> {
+    let content = encoding::encode(buffer.text_buffer.to_string().as_str(), &format)?;
+
+    // Write through symlinks to the file they point at
+    let path = fs::canonicalize(filename).unwrap_or_else(|_| PathBuf::from(filename));
+    let metadata = fs::metadata(&path).ok();
+
+    let make_backup = metadata.is_some() && (options.backup || options.writebackup);
+    let backup = backup_path(&path);
+    let in_place = match (&metadata, options.backupcopy) {
+        (None, _) | (_, BackupCopy::No) => false,
+        (Some(_), BackupCopy::Yes) => true,
+        (Some(metadata), BackupCopy::Auto) => metadata.nlink() > 1,
+    };
+
+    if in_place {
+        if make_backup {
+            fs::copy(&path, &backup)?;
+        }
+        write_in_place(&path, content.as_slice())?;
+    } else {
+        let backup = Some(backup.as_path()).filter(|_| make_backup);
+        if let Err(e) = replace_file(&path, backup, content.as_slice(), metadata.as_ref()) {
+            // Without write access to the directory, overwriting the file
+            // itself is the only way left
+            if metadata.is_none() {
+                return Err(e.into());
+            }
+            write_in_place(&path, content.as_slice())?;
+        }
+    }
+
+    if make_backup && !options.backup {
+        let _ = fs::remove_file(&backup);
+    }
+    Ok(FileInfo {
+        saved_tick: buffer.text_buffer.change_tick,
+        format,
+        saved_format: format,
+        ..FileInfo::read(filename)
+    })
+}
+
+fn backup_path(path: &Path) -> PathBuf {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push("~");
+    PathBuf::from(backup)
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map_or("".into(), |name| name.to_string_lossy());
+    path.with_file_name(format!(".{}.{}.tmp", name, process::id()))
+}
+
+/**
+ * Write the content to a temporary file and rename it over the original,
+ * after keeping the original as the backup if there's one to make
+ */
+fn replace_file(
+    path: &Path,
+    backup: Option<&Path>,
+    content: &[u8],
+    original: Option<&Metadata>,
+) -> io::Result<()> {
+    let temp = temp_path(path);
+    let result = write_temp(&temp, content, original).and_then(|_| {
+        if let Some(backup) = backup {
+            link_backup(path, backup)?;
+        }
+        fs::rename(&temp, path)
+    });
+    match result {
+        Ok(()) => sync_dir(path),
+        Err(_) => {
+            let _ = fs::remove_file(&temp);
+        }
+    }
+    result
+}
+
+/**
+ * Write a new file with the original's permissions and owner
+ */
+fn write_temp(path: &Path, content: &[u8], original: Option<&Metadata>) -> io::Result<()> {
+    let mut file = OpenOptions::new().write(true).create_new(true).open(path)?;
+    file.write_all(content)?;
+
+    if let Some(original) = original {
+        file.set_permissions(original.permissions())?;
+        // Only root can give files away, so failing here is expected
+        let _ = fchown(&file, Some(original.uid()), Some(original.gid()));
+    }
+    file.sync_all()
+}
+
+fn write_in_place(path: &Path, content: &[u8]) -> io::Result<()> {
+    let mut file = OpenOptions::new().write(true).truncate(true).open(path)?;
+    file.write_all(content)?;
+    file.sync_all()
+}
+
+/**
+ * Keep the original as the backup. Hard linking leaves the original in
+ * place until the rename replaces it, but not every file system has links.
+ */
+fn link_backup(path: &Path, backup: &Path) -> io::Result<()> {
+    let _ = fs::remove_file(backup);
+    if fs::hard_link(path, backup).is_err() {
+        fs::copy(path, backup)?;
+    }
     Ok(())
 }
+
+/**
+ * Make the rename itself durable
+ */
+fn sync_dir(path: &Path) {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn buffer(text: &str) -> Buffer<PieceTableBuffer> {
+        Buffer::new(Box::new(PieceTableBuffer::new(text.to_string())))
+    }
+
+    #[test]
+    fn test_write_file() {
+        let dir = std::env::temp_dir().join(format!("vik-write-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("file.txt");
+        let filename = path.to_str().unwrap();
+
+        let mut options = Options::new();
+        write_file(filename, &buffer("one\n"), FileFormat::default(), &options).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+
+        // Atomic by default, keeping permissions and no leftover files
+        write_file(filename, &buffer("two\n"), FileFormat::default(), &options).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "two\n");
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o640
+        );
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        // Hard links survive when writing in place, and the backup is kept
+        let link = dir.join("link.txt");
+        fs::hard_link(&path, &link).unwrap();
+        options.backup = true;
+        write_file(
+            filename,
+            &buffer("three\n"),
+            FileFormat::default(),
+            &options,
+        )
+        .unwrap();
+        assert_eq!(fs::read_to_string(&link).unwrap(), "three\n");
+        assert_eq!(fs::read_to_string(backup_path(&path)).unwrap(), "two\n");
+
+        // A file in a directory that can't be written to is still written
+        fs::remove_file(&link).unwrap();
+        options.backupcopy = BackupCopy::No;
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o555)).unwrap();
+        let result = write_file(filename, &buffer("four\n"), FileFormat::default(), &options);
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).unwrap();
+        result.unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "four\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        let path = std::env::temp_dir().join(format!("vik-missing-{}", process::id()));
+        let (buffer, info) = load_file(path.to_str().unwrap()).unwrap();
+        assert_eq!(buffer.text_buffer.to_string(), "");
+        assert!(info.new_file);
+        assert!(!info.readonly);
+
+        // Errors other than a missing file are still errors
+        assert!(load_file(std::env::temp_dir().to_str().unwrap()).is_err());
+    }
+}
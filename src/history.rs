@@ -0,0 +1,58 @@
+const MAX_ENTRIES: usize = 100;
+
+/**
+ * Previously entered command lines or searches, oldest first
+ */
+#[derive(Debug, Clone)]
+pub struct History {
+    entries: Vec<String>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        History { entries: vec![] }
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /**
+     * Add an entry, moving it to the end if it's already there
+     */
+    pub fn push(&mut self, entry: &str) {
+        if entry.is_empty() {
+            return;
+        }
+
+        self.entries.retain(|e| e != entry);
+        self.entries.push(entry.to_string());
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.remove(0);
+        }
+    }
+
+    /**
+     * The closest older entry than idx starting with prefix. None for idx
+     * starts from the newest entry.
+     */
+    pub fn prev(&self, idx: Option<usize>, prefix: &str) -> Option<usize> {
+        let end = idx.unwrap_or(self.entries.len());
+        (0..end)
+            .rev()
+            .find(|&i| self.entries[i].starts_with(prefix))
+    }
+
+    /**
+     * The closest newer entry than idx starting with prefix
+     */
+    pub fn next(&self, idx: usize, prefix: &str) -> Option<usize> {
+        (idx + 1..self.entries.len()).find(|&i| self.entries[i].starts_with(prefix))
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History::new()
+    }
+}
@@ -0,0 +1,426 @@
+use crate::editor::Editor;
+use crate::event::Event;
+use crate::state::State;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use termion::event::Key;
+
+// How many mappings can expand without a key being handled, before
+// giving up on a mapping that maps to itself
+const MAX_DEPTH: usize = 1000;
+
+/**
+ * Names for keys in `<Name>` notation. The first name for a key is the one
+ * it's shown with.
+ */
+const KEY_NAMES: &[(&str, Key)] = &[
+    ("CR", Key::Char('\n')),
+    ("Enter", Key::Char('\n')),
+    ("Return", Key::Char('\n')),
+    ("Tab", Key::Char('\t')),
+    ("Space", Key::Char(' ')),
+    ("lt", Key::Char('<')),
+    ("Bslash", Key::Char('\\')),
+    ("Bar", Key::Char('|')),
+    ("Esc", Key::Esc),
+    ("BS", Key::Backspace),
+    ("Del", Key::Delete),
+    ("Insert", Key::Insert),
+    ("Up", Key::Up),
+    ("Down", Key::Down),
+    ("Left", Key::Left),
+    ("Right", Key::Right),
+    ("Home", Key::Home),
+    ("End", Key::End),
+    ("PageUp", Key::PageUp),
+    ("PageDown", Key::PageDown),
+    ("S-Tab", Key::BackTab),
+    ("Nul", Key::Null),
+    // termion reads the control keys past C-z as digits
+    ("C-\\", Key::Ctrl('4')),
+    ("C-]", Key::Ctrl('5')),
+    ("C-^", Key::Ctrl('6')),
+    ("C-_", Key::Ctrl('7')),
+];
+
+/**
+ * Keys waiting to be handled, and whether each can still be mapped
+ */
+pub type Typeahead = VecDeque<(Key, bool)>;
+
+/**
+ * The modes a mapping can apply in
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    Normal,
+    Visual,
+    Insert,
+}
+
+impl Mode {
+    fn name(&self) -> &'static str {
+        match self {
+            Mode::Normal => "n",
+            Mode::Visual => "v",
+            Mode::Insert => "i",
+        }
+    }
+
+    /**
+     * The mode keys for the state are mapped in. Keys that complete a
+     * command, like the mark name after `m`, aren't mapped.
+     */
+    fn of(state: &State) -> Option<Mode> {
+        match state {
+            State::Normal(s) if !s.is_pending() => Some(Mode::Normal),
+            State::Visual(s) if !s.is_pending() => Some(Mode::Visual),
+            State::Insert(_) => Some(Mode::Insert),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mapping {
+    pub mode: Mode,
+    pub lhs: Vec<Key>,
+    pub rhs: Vec<Key>,
+    // The keys of a noremap mapping aren't mapped again
+    pub noremap: bool,
+}
+
+/**
+ * What the keys waiting to be handled match
+ */
+enum Lookup {
+    None,
+    // The keys start a longer mapping, so wait for more
+    Pending,
+    Found(Mapping),
+}
+
+pub struct Keymap {
+    mappings: Vec<Mapping>,
+}
+
+impl Keymap {
+    pub fn new() -> Self {
+        Keymap { mappings: vec![] }
+    }
+
+    /**
+     * Add a mapping, replacing any for the same keys in its mode
+     */
+    pub fn map(&mut self, mapping: Mapping) {
+        self.unmap(mapping.mode, &mapping.lhs);
+        self.mappings.push(mapping);
+    }
+
+    /**
+     * Remove a mapping, returning whether there was one
+     */
+    pub fn unmap(&mut self, mode: Mode, lhs: &[Key]) -> bool {
+        let count = self.mappings.len();
+        self.mappings.retain(|m| !(m.mode == mode && m.lhs == lhs));
+        self.mappings.len() != count
+    }
+
+    /**
+     * One line per mapping in the modes that starts with the prefix. A
+     * mapping that's the same in normal and visual mode is shown once,
+     * without a mode.
+     */
+    pub fn listing(&self, modes: &[Mode], prefix: &[Key]) -> Vec<String> {
+        let mappings = self
+            .mappings
+            .iter()
+            .filter(|m| modes.contains(&m.mode) && m.lhs.starts_with(prefix));
+        let shared = |m: &Mapping| {
+            modes.contains(&Mode::Normal)
+                && modes.contains(&Mode::Visual)
+                && self.mappings.iter().any(|other| {
+                    other.mode != m.mode
+                        && other.mode != Mode::Insert
+                        && (&other.lhs, &other.rhs, other.noremap) == (&m.lhs, &m.rhs, m.noremap)
+                })
+        };
+
+        mappings
+            .filter(|m| !(m.mode == Mode::Visual && shared(m)))
+            .map(|m| {
+                let mode = if shared(m) { " " } else { m.mode.name() };
+                let noremap = if m.noremap { "*" } else { " " };
+                format!(
+                    "{}  {:<11} {}{}",
+                    mode,
+                    format_keys(&m.lhs),
+                    noremap,
+                    format_keys(&m.rhs)
+                )
+            })
+            .collect()
+    }
+
+    /**
+     * The longest mapping the keys start with, unless they could still
+     * become a longer one
+     */
+    fn lookup(&self, mode: Mode, keys: &[Key], timed_out: bool) -> Lookup {
+        let mappings = self.mappings.iter().filter(|m| m.mode == mode);
+        if !timed_out
+            && mappings
+                .clone()
+                .any(|m| m.lhs.len() > keys.len() && m.lhs.starts_with(keys))
+        {
+            return Lookup::Pending;
+        }
+        mappings
+            .filter(|m| keys.starts_with(&m.lhs))
+            .max_by_key(|m| m.lhs.len())
+            .map_or(Lookup::None, |m| Lookup::Found(m.clone()))
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap::new()
+    }
+}
+
+/**
+ * Parse keys in `<Name>` notation, like `<C-w>j` or `<leader>w<CR>`.
+ * Anything that isn't a key name is taken literally.
+ */
+pub fn parse_keys(text: &str, leader: &str) -> Vec<Key> {
+    let mut keys = vec![];
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            let named = rest
+                .find('>')
+                .and_then(|end| Some((parse_name(&rest[1..end], leader)?, end)));
+            if let Some((mut named, end)) = named {
+                keys.append(&mut named);
+                rest = &rest[end + 1..];
+                continue;
+            }
+        }
+        keys.push(Key::Char(c));
+        rest = &rest[c.len_utf8()..];
+    }
+    keys
+}
+
+fn parse_name(name: &str, leader: &str) -> Option<Vec<Key>> {
+    if name.eq_ignore_ascii_case("leader") {
+        return Some(parse_keys(leader, ""));
+    }
+    if let Some((_, key)) = KEY_NAMES.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)) {
+        return Some(vec![*key]);
+    }
+
+    let mut chars = name.chars();
+    let key = match (chars.next(), chars.next(), chars.next(), chars.next()) {
+        (Some('C'), Some('-'), Some(c), None) | (Some('c'), Some('-'), Some(c), None) => {
+            Key::Ctrl(c.to_ascii_lowercase())
+        }
+        (Some('M'), Some('-'), Some(c), None)
+        | (Some('m'), Some('-'), Some(c), None)
+        | (Some('A'), Some('-'), Some(c), None)
+        | (Some('a'), Some('-'), Some(c), None) => Key::Alt(c),
+        (Some('F'), ..) | (Some('f'), ..) => Key::F(name[1..].parse().ok()?),
+        _ => return None,
+    };
+    Some(vec![key])
+}
+
+/**
+ * Show keys in the notation they're parsed from
+ */
+pub fn format_keys(keys: &[Key]) -> String {
+    keys.iter()
+        .map(|key| match KEY_NAMES.iter().find(|(_, k)| k == key) {
+            Some((name, _)) => format!("<{}>", name),
+            None => match key {
+                Key::Char(c) => c.to_string(),
+                Key::Ctrl(c) => format!("<C-{}>", c),
+                Key::Alt(c) => format!("<M-{}>", c),
+                Key::F(n) => format!("<F{}>", n),
+                key => format!("{:?}", key),
+            },
+        })
+        .collect()
+}
+
+impl Editor {
+    /**
+     * Handle a typed key, once it's clear whether it starts a mapping
+     */
+    pub fn handle_key(&mut self, key: Key) {
+        self.resolve_pending_keys();
+        self.typeahead.push_back((key, true));
+        self.process_typeahead(false);
+    }
+
+    /**
+     * How long until keys waiting on a longer mapping time out
+     */
+    pub fn pending_keys_timeout(&self) -> Option<Duration> {
+        let timeout = Duration::from_millis(self.options.timeoutlen as u64);
+        self.typeahead_since
+            .map(|since| timeout.checked_sub(since.elapsed()).unwrap_or_default())
+    }
+
+    /**
+     * Once `timeoutlen` has passed, keys waiting on a longer mapping are
+     * handled as they are
+     */
+    pub fn resolve_pending_keys(&mut self) {
+        if self.pending_keys_timeout() == Some(Duration::from_millis(0)) {
+            self.flush_pending_keys();
+        }
+    }
+
+    /**
+     * Handle keys waiting on a longer mapping without waiting any more
+     */
+    pub fn flush_pending_keys(&mut self) {
+        self.process_typeahead(true);
+    }
+
+    /**
+     * Handle keys as if typed, without waiting for any more. Keys already
+     * waiting are left for after.
+     */
+    pub fn execute_keys(&mut self, keys: &[Key], remap: bool) {
+        let keys = keys.iter().map(|&key| (key, remap)).collect();
+        let typeahead = std::mem::replace(&mut self.typeahead, keys);
+        let since = self.typeahead_since.take();
+        self.process_typeahead(true);
+        self.typeahead = typeahead;
+        self.typeahead_since = since;
+    }
+
+    /**
+     * Expand mappings at the front of the typeahead and pass the keys on
+     * to the current state, until it's empty or waiting on more keys
+     */
+    fn process_typeahead(&mut self, timed_out: bool) {
+        let mut depth = 0;
+        while let Some(&(key, remap)) = self.typeahead.front() {
+            let mode = Mode::of(self.state()).filter(|_| remap);
+            if let Some(mode) = mode {
+                let keys = self
+                    .typeahead
+                    .iter()
+                    .take_while(|(_, remap)| *remap)
+                    .map(|(key, _)| *key)
+                    .collect::<Vec<Key>>();
+                match self.keymap.lookup(mode, &keys, timed_out) {
+                    Lookup::Pending => {
+                        self.typeahead_since = Some(Instant::now());
+                        return;
+                    }
+                    Lookup::Found(mapping) => {
+                        depth += 1;
+                        if depth > MAX_DEPTH {
+                            self.typeahead.clear();
+                            self.messages.error("Recursive mapping");
+                            break;
+                        }
+
+                        // A mapping that starts with its own keys doesn't map them again
+                        let skip = if mapping.rhs.starts_with(&mapping.lhs) {
+                            mapping.lhs.len()
+                        } else {
+                            0
+                        };
+                        self.typeahead.drain(..mapping.lhs.len());
+                        for (i, key) in mapping.rhs.iter().enumerate().rev() {
+                            self.typeahead
+                                .push_front((*key, !mapping.noremap && i >= skip));
+                        }
+                        continue;
+                    }
+                    Lookup::None => (),
+                }
+            }
+
+            self.typeahead.pop_front();
+            self.dispatch(Event::Key(key));
+        }
+        self.typeahead_since = None;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::buffer::Buffer;
+    use crate::ex::run;
+    use crate::text::{PieceTableBuffer, TextBuffer};
+
+    #[test]
+    fn test_parse_keys() {
+        let keys = parse_keys("<leader>w<CR><c-W>x<lt><F12><Nope>", ",");
+        assert_eq!(
+            keys,
+            vec![
+                Key::Char(','),
+                Key::Char('w'),
+                Key::Char('\n'),
+                Key::Ctrl('w'),
+                Key::Char('x'),
+                Key::Char('<'),
+                Key::F(12),
+                Key::Char('<'),
+                Key::Char('N'),
+                Key::Char('o'),
+                Key::Char('p'),
+                Key::Char('e'),
+                Key::Char('>'),
+            ]
+        );
+        assert_eq!(format_keys(&keys[..7]), ",w<CR><C-w>x<lt><F12>");
+        assert_eq!(parse_keys("<C-^>", ""), vec![Key::Ctrl('6')]);
+        assert_eq!(format_keys(&[Key::Ctrl('6')]), "<C-^>");
+    }
+
+    #[test]
+    fn test_mappings() {
+        let mut editor = Editor::new();
+        let text = "one\ntwo\n".to_string();
+        editor.text_buffer = Buffer::new(Box::new(PieceTableBuffer::new(text)));
+        for command in &["nnoremap x dd", "nmap Q x", "inoremap jk <Esc>"] {
+            run(command, &mut editor).unwrap();
+        }
+        let type_keys = |editor: &mut Editor, keys: &str| {
+            for c in keys.chars() {
+                editor.handle_key(Key::Char(c));
+            }
+        };
+
+        // Q maps to x, which is remapped to dd
+        type_keys(&mut editor, "Q");
+        assert_eq!(editor.text_buffer.text_buffer.to_string(), "two\n");
+
+        // j waits to see if it's jk, and is inserted when it isn't
+        type_keys(&mut editor, "ijajk");
+        assert_eq!(editor.text_buffer.text_buffer.to_string(), "jatwo\n");
+        assert_eq!(editor.mode(), "NORMAL");
+
+        // After timeoutlen, j is inserted on the next tick
+        run("set tm=0", &mut editor).unwrap();
+        type_keys(&mut editor, "ij");
+        assert_eq!(editor.text_buffer.text_buffer.to_string(), "jatwo\n");
+        editor.handle_event(Event::Tick);
+        assert_eq!(editor.text_buffer.text_buffer.to_string(), "jajtwo\n");
+        type_keys(&mut editor, "k");
+        assert_eq!(editor.text_buffer.text_buffer.to_string(), "jajktwo\n");
+        assert_eq!(editor.mode(), "INSERT");
+
+        let listing = editor.keymap.listing(&[Mode::Normal, Mode::Visual], &[]);
+        assert_eq!(listing, vec!["n  x           *dd", "n  Q            x"]);
+    }
+}
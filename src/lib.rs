@@ -2,11 +2,28 @@
 #![feature(generic_associated_types)]
 
 pub mod buffer;
+pub mod buffer_list;
+pub mod cmdline_window;
+pub mod colorscheme;
+pub mod config;
 pub mod editor;
+pub mod encoding;
 pub mod event;
+pub mod ex;
 pub mod file;
+pub mod history;
+pub mod keymap;
 pub mod logger;
+pub mod message;
+pub mod mouse;
+pub mod options;
+pub mod register;
+pub mod scroll;
+pub mod search;
 pub mod state;
+pub mod syntax;
+pub mod tab;
 pub mod text;
 mod text_object;
 pub mod ui;
+pub mod window;
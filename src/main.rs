@@ -1,12 +1,12 @@
 use clap::{App, Arg};
 use log::debug;
 use std::io;
-use termion::input::TermRead;
+use termion::input::MouseTerminal;
 use termion::raw::IntoRawMode;
 use tui::backend::TermionBackend;
 use tui::Terminal;
 use vik::editor::Editor;
-use vik::event::Event;
+use vik::event::{Events, PasteTerminal};
 use vik::logger::init_logger;
 use vik::ui::draw;
 
@@ -19,35 +19,32 @@ fn main() {
         Arg::with_name("FILE")
             .index(1)
             .required(false)
-            .help("The file to open"),
+            .multiple(true)
+            .help("The files to open"),
     );
     let matches = clap_app.get_matches();
 
     let stdout = io::stdout().into_raw_mode().unwrap();
+    let stdout = PasteTerminal::from(MouseTerminal::from(stdout));
     let backend = TermionBackend::new(stdout);
     let mut terminal = Terminal::new(backend).unwrap();
-    let mut keys = io::stdin().keys();
+    let mut events = Events::new();
 
-    let mut editor = match matches.value_of("FILE") {
-        Some(filename) => Editor::from_file(filename.to_string()),
-        None => Editor::new(),
-    };
+    let filenames = matches
+        .values_of("FILE")
+        .map_or(vec![], |values| values.map(String::from).collect());
+    let mut editor = Editor::from_files(&filenames);
 
     // Initial draw
     draw(&mut editor, &mut terminal).unwrap();
 
     debug!("Initialized");
 
+    // Redraw after every event, including ticks, so timers can change
+    // what's shown
     while editor.running {
-        let mut should_draw = false;
-
-        if let Some(Ok(key)) = keys.next() {
-            editor.handle_event(Event::Key(key));
-            should_draw = true;
-        }
-
-        if should_draw {
-            draw(&mut editor, &mut terminal).unwrap();
-        }
+        let event = events.next(editor.next_timeout());
+        editor.handle_event(event);
+        draw(&mut editor, &mut terminal).unwrap();
     }
 }
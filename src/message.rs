@@ -0,0 +1,77 @@
+const MAX_HISTORY: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MessageKind {
+    Info,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub text: String,
+    pub kind: MessageKind,
+}
+
+/**
+ * The message shown in the command line row, and every message shown
+ * so far for :messages
+ */
+#[derive(Debug, Clone)]
+pub struct Messages {
+    pub current: Option<Message>,
+    history: Vec<Message>,
+}
+
+impl Messages {
+    pub fn new() -> Self {
+        Messages {
+            current: None,
+            history: vec![],
+        }
+    }
+
+    pub fn history(&self) -> &[Message] {
+        &self.history
+    }
+
+    pub fn info(&mut self, text: &str) {
+        self.show(text, MessageKind::Info);
+    }
+
+    pub fn error(&mut self, text: &str) {
+        self.show(text, MessageKind::Error);
+    }
+
+    /**
+     * Show text without adding it to the history, like the :messages
+     * output itself
+     */
+    pub fn show_only(&mut self, text: &str) {
+        self.current = Some(Message {
+            text: text.to_string(),
+            kind: MessageKind::Info,
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.current = None;
+    }
+
+    fn show(&mut self, text: &str, kind: MessageKind) {
+        let message = Message {
+            text: text.to_string(),
+            kind,
+        };
+        self.history.push(message.clone());
+        if self.history.len() > MAX_HISTORY {
+            self.history.remove(0);
+        }
+        self.current = Some(message);
+    }
+}
+
+impl Default for Messages {
+    fn default() -> Self {
+        Messages::new()
+    }
+}
@@ -0,0 +1,119 @@
+use crate::buffer::Position;
+use crate::editor::Editor;
+use crate::state::{State, VisualState};
+use termion::event::{MouseButton, MouseEvent};
+
+// How many lines each step of the wheel scrolls
+const WHEEL_LINES: isize = 3;
+
+impl Editor {
+    /**
+     * Clicks move the cursor, switching windows if needed, dragging
+     * selects in visual mode and the wheel scrolls the current window
+     */
+    pub fn handle_mouse(&mut self, event: MouseEvent) {
+        // Not partway through a command, or on the command line
+        match self.state() {
+            State::Normal(state) if !state.is_pending() => (),
+            State::Visual(_) | State::Insert(_) => (),
+            _ => return,
+        }
+
+        match event {
+            MouseEvent::Press(MouseButton::WheelUp, _, _) => self.scroll_lines(-WHEEL_LINES),
+            MouseEvent::Press(MouseButton::WheelDown, _, _) => self.scroll_lines(WHEEL_LINES),
+            MouseEvent::Press(MouseButton::Left, x, y) => {
+                // Positions are 1-based
+                let (x, y) = (x.saturating_sub(1), y.saturating_sub(1));
+                match self.window_at(x, y) {
+                    Some(id) if id == self.windows.current => (),
+                    Some(id) if self.cmdline_window.is_none() => self.switch_window(id),
+                    _ => return,
+                }
+
+                // A click ends any selection
+                if let State::Visual(_) = self.state() {
+                    if let Some(State::Visual(visual)) = self.state_stack.pop() {
+                        visual.set_marks(&mut self.text_buffer);
+                    }
+                }
+                self.move_cursor_to(x, y);
+            }
+            MouseEvent::Hold(x, y) => {
+                match self.state() {
+                    State::Normal(_) => {
+                        let anchor = self.text_buffer.cursor();
+                        self.state_stack
+                            .push(State::Visual(VisualState::new(anchor)));
+                    }
+                    State::Visual(_) => (),
+                    _ => return,
+                }
+                self.move_cursor_to(x.saturating_sub(1), y.saturating_sub(1));
+            }
+            _ => (),
+        }
+    }
+
+    /**
+     * Move the cursor to the text drawn at a screen position in the current
+     * window. Positions outside of it go to the nearest row.
+     */
+    fn move_cursor_to(&mut self, x: u16, y: u16) {
+        let state = &self.text_window_state;
+        let (line, display_col) = match state.position_at(
+            x.saturating_sub(state.area.x),
+            y.saturating_sub(state.area.y),
+        ) {
+            Some(position) => position,
+            None => return,
+        };
+        let col = self.text_buffer.col_at_display(line, display_col);
+        self.text_buffer.move_cursor(Position::new(line, col));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::buffer::Buffer;
+    use crate::event::Event;
+    use crate::text::{PieceTableBuffer, TextBuffer};
+    use termion::event::Key;
+    use tui::layout::Rect;
+
+    #[test]
+    fn test_mouse() {
+        let mut editor = Editor::new();
+        let text = "\tone\n".repeat(20);
+        editor.text_buffer = Buffer::new(Box::new(PieceTableBuffer::new(text)));
+        let state = &mut editor.text_window_state;
+        state.area = Rect::new(0, 0, 20, 5);
+        state.rows = (0..5).map(|line| (line, 0)).collect();
+
+        // The tab before "one" takes 8 columns
+        editor.handle_mouse(MouseEvent::Press(MouseButton::Left, 10, 2));
+        assert_eq!(editor.text_buffer.cursor(), Position::new(1, 2));
+
+        editor.handle_mouse(MouseEvent::Hold(3, 4));
+        assert_eq!(editor.mode(), "VISUAL");
+        assert_eq!(editor.text_buffer.cursor(), Position::new(3, 0));
+
+        // Scrolling down a step leaves the cursor on the top line
+        editor.handle_mouse(MouseEvent::Press(MouseButton::Left, 1, 1));
+        assert_eq!(editor.mode(), "NORMAL");
+        editor.handle_mouse(MouseEvent::Press(MouseButton::WheelDown, 1, 1));
+        assert_eq!(editor.text_window_state.offset, 3);
+        assert_eq!(editor.text_buffer.cursor(), Position::new(3, 0));
+
+        // Clicks land on byte columns, and x takes the whole char before them
+        let text = "éxyz\n".to_string();
+        editor.text_buffer = Buffer::new(Box::new(PieceTableBuffer::new(text)));
+        editor.text_window_state.offset = 0;
+        editor.text_window_state.rows = vec![(0, 0)];
+        editor.handle_mouse(MouseEvent::Press(MouseButton::Left, 2, 1));
+        assert_eq!(editor.text_buffer.cursor(), Position::new(0, 2));
+        editor.handle_event(Event::Key(Key::Char('x')));
+        assert_eq!(editor.text_buffer.text_buffer.to_string(), "xyz\n");
+    }
+}
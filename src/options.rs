@@ -0,0 +1,389 @@
+use crate::editor::Editor;
+use crate::encoding::{Encoding, FileFormat, LineEnding};
+use crate::ex::ExError;
+use std::fmt;
+
+/**
+ * Where an option's value lives. Buffer and window options are local,
+ * and also have a global value that new buffers and windows start with.
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scope {
+    Global,
+    Buffer,
+    Window,
+}
+
+/**
+ * Every option with its abbreviation and scope
+ */
+pub const OPTIONS: &[(&str, &str, Scope)] = &[
+    ("backup", "bk", Scope::Global),
+    ("backupcopy", "bkc", Scope::Global),
+    ("bomb", "bomb", Scope::Buffer),
+    ("expandtab", "et", Scope::Buffer),
+    ("fileencoding", "fenc", Scope::Buffer),
+    ("fileformat", "ff", Scope::Buffer),
+    ("hlsearch", "hls", Scope::Global),
+    ("ignorecase", "ic", Scope::Global),
+    ("incsearch", "is", Scope::Global),
+    ("mapleader", "mapleader", Scope::Global),
+    ("number", "nu", Scope::Window),
+    ("scrolloff", "so", Scope::Global),
+    ("shiftwidth", "sw", Scope::Buffer),
+    ("smartcase", "scs", Scope::Global),
+    ("tabstop", "ts", Scope::Buffer),
+    ("timeoutlen", "tm", Scope::Global),
+    ("wrap", "wrap", Scope::Window),
+    ("writebackup", "wb", Scope::Global),
+];
+
+/**
+ * The full name and scope of an option, from either of its names
+ */
+pub fn find(name: &str) -> Option<(&'static str, Scope)> {
+    OPTIONS
+        .iter()
+        .find(|(full, short, _)| *full == name || *short == name)
+        .map(|(full, _, scope)| (*full, *scope))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Number(usize),
+    String(String),
+}
+
+impl Value {
+    /**
+     * Parse text as the same type of value as this one
+     */
+    pub fn parse_like(&self, text: &str) -> Option<Value> {
+        match self {
+            Value::Bool(_) => None,
+            Value::Number(_) => text.parse().ok().map(Value::Number),
+            Value::String(_) => Some(Value::String(text.to_string())),
+        }
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    fn as_number(&self) -> Option<usize> {
+        match self {
+            Value::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(value) => Some(value.as_str()),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Number(value) => write!(f, "{}", value),
+            Value::String(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/**
+ * How the backup is made when writing a file. `Yes` copies the file and
+ * overwrites the original in place, keeping hard links intact. `No`
+ * writes a new file and renames it over the original. `Auto` picks `Yes`
+ * only for files with more than one link.
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BackupCopy {
+    Yes,
+    No,
+    Auto,
+}
+
+impl BackupCopy {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "yes" => Some(BackupCopy::Yes),
+            "no" => Some(BackupCopy::No),
+            "auto" => Some(BackupCopy::Auto),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            BackupCopy::Yes => "yes",
+            BackupCopy::No => "no",
+            BackupCopy::Auto => "auto",
+        }
+    }
+}
+
+/**
+ * Options local to a buffer
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct BufferOptions {
+    pub tabstop: usize,
+    pub shiftwidth: usize,
+    pub expandtab: bool,
+}
+
+impl BufferOptions {
+    pub fn new() -> Self {
+        BufferOptions {
+            tabstop: 8,
+            shiftwidth: 4,
+            expandtab: false,
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<Value> {
+        Some(match name {
+            "tabstop" => Value::Number(self.tabstop),
+            "shiftwidth" => Value::Number(self.shiftwidth),
+            "expandtab" => Value::Bool(self.expandtab),
+            _ => return None,
+        })
+    }
+
+    fn set(&mut self, name: &str, value: &Value) -> Option<()> {
+        match name {
+            "tabstop" => self.tabstop = value.as_number().filter(|&ts| ts > 0)?,
+            "shiftwidth" => self.shiftwidth = value.as_number()?,
+            "expandtab" => self.expandtab = value.as_bool()?,
+            _ => return None,
+        }
+        Some(())
+    }
+}
+
+impl Default for BufferOptions {
+    fn default() -> Self {
+        BufferOptions::new()
+    }
+}
+
+/**
+ * Options local to a window
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowOptions {
+    pub number: bool,
+    pub wrap: bool,
+}
+
+impl WindowOptions {
+    pub fn new() -> Self {
+        WindowOptions {
+            number: false,
+            wrap: true,
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<Value> {
+        Some(match name {
+            "number" => Value::Bool(self.number),
+            "wrap" => Value::Bool(self.wrap),
+            _ => return None,
+        })
+    }
+
+    fn set(&mut self, name: &str, value: &Value) -> Option<()> {
+        match name {
+            "number" => self.number = value.as_bool()?,
+            "wrap" => self.wrap = value.as_bool()?,
+            _ => return None,
+        }
+        Some(())
+    }
+}
+
+impl Default for WindowOptions {
+    fn default() -> Self {
+        WindowOptions::new()
+    }
+}
+
+/**
+ * Global options, and the global values of local ones
+ */
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub hlsearch: bool,
+    pub incsearch: bool,
+    pub ignorecase: bool,
+    pub smartcase: bool,
+    pub scrolloff: usize,
+
+    // What <leader> stands for in mappings, and how long to wait for a
+    // mapping's next key in milliseconds
+    pub mapleader: String,
+    pub timeoutlen: usize,
+
+    // Keep the backup after writing, and make one for the duration of a write
+    pub backup: bool,
+    pub writebackup: bool,
+    pub backupcopy: BackupCopy,
+
+    pub buffer: BufferOptions,
+    pub window: WindowOptions,
+}
+
+impl Options {
+    pub fn new() -> Self {
+        Options {
+            hlsearch: true,
+            incsearch: true,
+            ignorecase: false,
+            smartcase: false,
+            scrolloff: 0,
+            mapleader: "\\".to_string(),
+            timeoutlen: 1000,
+            backup: false,
+            writebackup: true,
+            backupcopy: BackupCopy::Auto,
+            buffer: BufferOptions::new(),
+            window: WindowOptions::new(),
+        }
+    }
+
+    /**
+     * Whether a search for the pattern ignores case. With smartcase, an
+     * uppercase letter makes it match case again.
+     */
+    pub fn ignore_case(&self, pattern: &str) -> bool {
+        self.ignorecase && !(self.smartcase && pattern.chars().any(char::is_uppercase))
+    }
+
+    fn get(&self, name: &str) -> Option<Value> {
+        Some(match name {
+            "hlsearch" => Value::Bool(self.hlsearch),
+            "incsearch" => Value::Bool(self.incsearch),
+            "ignorecase" => Value::Bool(self.ignorecase),
+            "smartcase" => Value::Bool(self.smartcase),
+            "scrolloff" => Value::Number(self.scrolloff),
+            "mapleader" => Value::String(self.mapleader.clone()),
+            "timeoutlen" => Value::Number(self.timeoutlen),
+            "backup" => Value::Bool(self.backup),
+            "writebackup" => Value::Bool(self.writebackup),
+            "backupcopy" => Value::String(self.backupcopy.name().to_string()),
+            _ => return self.buffer.get(name).or_else(|| self.window.get(name)),
+        })
+    }
+
+    fn set(&mut self, name: &str, value: &Value) -> Option<()> {
+        match name {
+            "hlsearch" => self.hlsearch = value.as_bool()?,
+            "incsearch" => self.incsearch = value.as_bool()?,
+            "ignorecase" => self.ignorecase = value.as_bool()?,
+            "smartcase" => self.smartcase = value.as_bool()?,
+            "scrolloff" => self.scrolloff = value.as_number()?,
+            "mapleader" => self.mapleader = value.as_str()?.to_string(),
+            "timeoutlen" => self.timeoutlen = value.as_number()?,
+            "backup" => self.backup = value.as_bool()?,
+            "writebackup" => self.writebackup = value.as_bool()?,
+            "backupcopy" => self.backupcopy = BackupCopy::parse(value.as_str()?)?,
+            _ => {
+                return self
+                    .buffer
+                    .set(name, value)
+                    .or_else(|| self.window.set(name, value))
+            }
+        }
+        Some(())
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options::new()
+    }
+}
+
+/**
+ * The file format options, which belong to the buffer's file
+ */
+fn get_format(format: &FileFormat, name: &str) -> Option<Value> {
+    Some(match name {
+        "fileformat" => Value::String(format.line_ending.name().to_string()),
+        "fileencoding" => Value::String(format.encoding.name().to_string()),
+        "bomb" => Value::Bool(format.bom),
+        _ => return None,
+    })
+}
+
+fn set_format(format: &mut FileFormat, name: &str, value: &Value) -> Option<()> {
+    match name {
+        "fileformat" => format.line_ending = LineEnding::parse(value.as_str()?)?,
+        "fileencoding" => format.encoding = Encoding::parse(value.as_str()?)?,
+        "bomb" => format.bom = value.as_bool()?,
+        _ => return None,
+    }
+    Some(())
+}
+
+impl Editor {
+    /**
+     * An option's value for the current buffer and window
+     */
+    pub fn option(&self, name: &str) -> Option<Value> {
+        let (name, scope) = find(name)?;
+        match scope {
+            Scope::Global => self.options.get(name),
+            Scope::Buffer => get_format(&self.file_info.format, name)
+                .or_else(|| self.text_buffer.options.get(name)),
+            Scope::Window => self.text_window_state.options.get(name),
+        }
+    }
+
+    /**
+     * An option's value when nothing has changed it
+     */
+    pub fn default_option(&self, name: &str) -> Option<Value> {
+        let (name, _) = find(name)?;
+        get_format(&FileFormat::default(), name).or_else(|| Options::new().get(name))
+    }
+
+    /**
+     * Set an option for the current buffer or window. Local options also
+     * change their global value, for new buffers and windows, unless
+     * `local_only`.
+     */
+    pub fn set_option(
+        &mut self,
+        name: &str,
+        value: &Value,
+        local_only: bool,
+    ) -> Result<(), ExError> {
+        let (name, scope) = find(name).ok_or_else(|| ExError::UnknownOption(name.to_string()))?;
+        let invalid = || ExError::Invalid(format!("Invalid argument: {}={}", name, value));
+
+        let set = match scope {
+            Scope::Global => self.options.set(name, value),
+            Scope::Buffer if get_format(&self.file_info.format, name).is_some() => {
+                set_format(&mut self.file_info.format, name, value)
+            }
+            Scope::Buffer => self.text_buffer.options.set(name, value),
+            Scope::Window => self.text_window_state.options.set(name, value),
+        };
+        set.ok_or_else(invalid)?;
+        if scope != Scope::Global && !local_only {
+            // File formats have no global value
+            let _ = self.options.set(name, value);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+
+pub const UNNAMED: char = '"';
+pub const BLACK_HOLE: char = '_';
+
+#[derive(Debug, Clone)]
+pub struct Register {
+    pub text: String,
+    pub linewise: bool,
+}
+
+impl Register {
+    pub fn new(text: String, linewise: bool) -> Self {
+        Register { text, linewise }
+    }
+}
+
+/**
+ * Yanked and deleted text. Every write also goes to the unnamed
+ * register, and uppercase names append to their lowercase register.
+ */
+#[derive(Debug, Clone)]
+pub struct Registers {
+    registers: HashMap<char, Register>,
+}
+
+impl Registers {
+    pub fn new() -> Self {
+        Registers {
+            registers: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, name: char) -> Option<&Register> {
+        self.registers.get(&name.to_ascii_lowercase())
+    }
+
+    pub fn set(&mut self, name: Option<char>, register: Register) {
+        let name = name.unwrap_or(UNNAMED);
+        if name == BLACK_HOLE {
+            return;
+        }
+
+        let register = match self.registers.get(&name.to_ascii_lowercase()) {
+            Some(existing) if name.is_ascii_uppercase() => Register::new(
+                existing.text.clone() + register.text.as_str(),
+                existing.linewise || register.linewise,
+            ),
+            _ => register,
+        };
+
+        self.registers.insert(UNNAMED, register.clone());
+        self.registers.insert(name.to_ascii_lowercase(), register);
+    }
+}
+
+impl Default for Registers {
+    fn default() -> Self {
+        Registers::new()
+    }
+}
@@ -0,0 +1,205 @@
+use crate::buffer::Position;
+use crate::editor::Editor;
+use crate::ex::last_line;
+use crate::ui::text_window::screen_rows;
+
+/**
+ * Where `zt`, `zz` and `zb` put the cursor line in the window
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScrollTo {
+    Top,
+    Center,
+    Bottom,
+}
+
+impl Editor {
+    /**
+     * Scroll the current window by lines, down for a positive count. The
+     * cursor only moves as far as it needs to stay `scrolloff` lines
+     * inside the window.
+     */
+    pub fn scroll_lines(&mut self, count: isize) {
+        let last = last_line(&*self.text_buffer.text_buffer);
+        let state = &mut self.text_window_state;
+        let offset = (state.offset as isize + count).max(0) as usize;
+        state.offset = offset.min(last);
+        self.keep_cursor_in_view();
+    }
+
+    /**
+     * Ctrl-d and Ctrl-u: scroll half a window, taking the cursor along the
+     * same number of lines
+     */
+    pub fn scroll_half_page(&mut self, down: bool) {
+        let count = (self.window_height() / 2).max(1) as isize;
+        let count = if down { count } else { -count };
+        let last = last_line(&*self.text_buffer.text_buffer) as isize;
+
+        let cursor = self.text_buffer.cursor();
+        let line = (cursor.line as isize + count).max(0).min(last) as usize;
+        self.text_buffer
+            .move_cursor(Position::new(line, cursor.col));
+        self.scroll_lines(count);
+    }
+
+    /**
+     * Ctrl-f and Ctrl-b: scroll a window at a time, keeping two lines of
+     * the last window in view
+     */
+    pub fn scroll_page(&mut self, down: bool) {
+        let count = self.window_height().saturating_sub(2).max(1) as isize;
+        self.scroll_lines(if down { count } else { -count });
+    }
+
+    /**
+     * Scroll the current window to show the cursor line at the top, center
+     * or bottom, as far as `scrolloff` allows
+     */
+    pub fn scroll_cursor_to(&mut self, to: ScrollTo) {
+        let height = self.window_height();
+        let scrolloff = self.scrolloff();
+        let line = self.text_buffer.cursor().line;
+        let cursor_rows = self.window_rows(line);
+
+        // The screen rows to fill with lines above the cursor
+        let above = match to {
+            ScrollTo::Top => (line.saturating_sub(scrolloff)..line)
+                .map(|line| self.window_rows(line))
+                .sum(),
+            ScrollTo::Center => height.saturating_sub(cursor_rows) / 2,
+            ScrollTo::Bottom => {
+                let last = last_line(&*self.text_buffer.text_buffer);
+                let below = (line + 1..=(line + scrolloff).min(last))
+                    .map(|line| self.window_rows(line))
+                    .sum::<usize>();
+                height.saturating_sub(cursor_rows + below)
+            }
+        };
+
+        let mut offset = line;
+        let mut used = 0;
+        while offset > 0 && used + self.window_rows(offset - 1) <= above {
+            used += self.window_rows(offset - 1);
+            offset -= 1;
+        }
+        self.text_window_state.offset = offset;
+    }
+
+    /**
+     * The first and last lines that fully fit in the current window, from
+     * where it's scrolled to
+     */
+    pub fn visible_lines(&self) -> (usize, usize) {
+        let height = self.window_height();
+        let last = last_line(&*self.text_buffer.text_buffer);
+
+        let mut bottom = self.text_window_state.offset;
+        let mut used = self.window_rows(bottom);
+        while bottom < last {
+            used += self.window_rows(bottom + 1);
+            if used > height {
+                break;
+            }
+            bottom += 1;
+        }
+        (self.text_window_state.offset, bottom)
+    }
+
+    /**
+     * Move the cursor onto the lines the current window shows, less
+     * `scrolloff` lines at either edge
+     */
+    fn keep_cursor_in_view(&mut self) {
+        let (top, bottom) = self.visible_lines();
+        let last = last_line(&*self.text_buffer.text_buffer);
+        let scrolloff = self.scrolloff();
+
+        // There's nothing to keep in view past the ends of the buffer
+        let top = if top == 0 { 0 } else { top + scrolloff };
+        let bottom = if bottom >= last {
+            bottom
+        } else {
+            bottom.saturating_sub(scrolloff)
+        };
+
+        let cursor = self.text_buffer.cursor();
+        let line = cursor.line.min(bottom.max(top)).max(top);
+        if line != cursor.line {
+            self.text_buffer
+                .move_cursor(Position::new(line, cursor.col));
+        }
+    }
+
+    /**
+     * The rows the current window last drew its text in
+     */
+    fn window_height(&self) -> usize {
+        (self.text_window_state.area.height as usize).max(1)
+    }
+
+    fn window_rows(&self, line: usize) -> usize {
+        let state = &self.text_window_state;
+        screen_rows(
+            &self.text_buffer,
+            line,
+            state.text_width(),
+            state.options.wrap,
+        )
+    }
+
+    /**
+     * `scrolloff`, limited the same way as when the window is drawn
+     */
+    fn scrolloff(&self) -> usize {
+        let height = self.window_height();
+        self.options.scrolloff.min(height.saturating_sub(1) / 2)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::buffer::Buffer;
+    use crate::event::Event;
+    use crate::text::PieceTableBuffer;
+    use termion::event::Key;
+    use tui::layout::Rect;
+
+    #[test]
+    fn test_scroll() {
+        let mut editor = Editor::new();
+        let text = "line\n".repeat(100);
+        editor.text_buffer = Buffer::new(Box::new(PieceTableBuffer::new(text)));
+        editor.text_window_state.area = Rect::new(0, 0, 20, 10);
+        editor.options.scrolloff = 2;
+        let keys = |editor: &mut Editor, keys: &[Key]| {
+            for key in keys {
+                editor.handle_event(Event::Key(*key));
+            }
+        };
+
+        keys(&mut editor, &[Key::Ctrl('d')]);
+        assert_eq!(editor.text_window_state.offset, 5);
+        assert_eq!(editor.text_buffer.cursor().line, 7);
+
+        // Ctrl-e drags the cursor along once it reaches scrolloff
+        keys(&mut editor, &[Key::Ctrl('e'), Key::Ctrl('e')]);
+        assert_eq!(editor.text_window_state.offset, 7);
+        assert_eq!(editor.text_buffer.cursor().line, 9);
+
+        keys(&mut editor, &[Key::Ctrl('f')]);
+        assert_eq!(editor.text_window_state.offset, 15);
+        assert_eq!(editor.text_buffer.cursor().line, 17);
+        keys(&mut editor, &[Key::Ctrl('b')]);
+        assert_eq!(editor.text_window_state.offset, 7);
+        assert_eq!(editor.text_buffer.cursor().line, 14);
+
+        keys(&mut editor, &[Key::Char('z'), Key::Char('t')]);
+        assert_eq!(editor.text_window_state.offset, 12);
+        keys(&mut editor, &[Key::Char('z'), Key::Char('z')]);
+        assert_eq!(editor.text_window_state.offset, 10);
+        keys(&mut editor, &[Key::Char('z'), Key::Char('b')]);
+        assert_eq!(editor.text_window_state.offset, 7);
+    }
+}
@@ -0,0 +1,159 @@
+use crate::buffer::Position;
+use crate::text::{Range, TextBuffer};
+use regex::{Regex, RegexBuilder};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Forward,
+    Backward,
+}
+
+impl Direction {
+    pub fn reverse(self) -> Self {
+        match self {
+            Direction::Forward => Direction::Backward,
+            Direction::Backward => Direction::Forward,
+        }
+    }
+
+    pub fn prompt(self) -> char {
+        match self {
+            Direction::Forward => '/',
+            Direction::Backward => '?',
+        }
+    }
+}
+
+/**
+ * A compiled search pattern and the direction it was entered with.
+ * Patterns use the regex crate's syntax.
+ */
+#[derive(Debug, Clone)]
+pub struct Search {
+    pub pattern: String,
+    pub regex: Regex,
+    pub direction: Direction,
+}
+
+impl Search {
+    pub fn new(
+        pattern: &str,
+        direction: Direction,
+        ignore_case: bool,
+    ) -> Result<Self, regex::Error> {
+        Ok(Search {
+            pattern: pattern.to_string(),
+            regex: RegexBuilder::new(pattern)
+                .case_insensitive(ignore_case)
+                .build()?,
+            direction,
+        })
+    }
+
+    /**
+     * Find the next match starting from (but not at) pos, wrapping around
+     * the end of the text.
+     */
+    pub fn find<T: TextBuffer>(
+        &self,
+        text: &T,
+        pos: Position,
+        direction: Direction,
+    ) -> Option<Range> {
+        let content = text.to_string();
+        let lines = content.split('\n').collect::<Vec<&str>>();
+        let line_count = lines.len();
+
+        match direction {
+            Direction::Forward => {
+                let first = self
+                    .line_matches(lines[pos.line])
+                    .into_iter()
+                    .find(|&(start, _)| start > pos.col);
+                if let Some((start, end)) = first {
+                    return Some(Range::new(Position::new(pos.line, start), end - start));
+                }
+
+                (1..=line_count)
+                    .map(|i| (pos.line + i) % line_count)
+                    .find_map(|line| {
+                        self.line_matches(lines[line]).first().map(|&(start, end)| {
+                            Range::new(Position::new(line, start), end - start)
+                        })
+                    })
+            }
+            Direction::Backward => {
+                let first = self
+                    .line_matches(lines[pos.line])
+                    .into_iter()
+                    .rev()
+                    .find(|&(start, _)| start < pos.col);
+                if let Some((start, end)) = first {
+                    return Some(Range::new(Position::new(pos.line, start), end - start));
+                }
+
+                (1..=line_count)
+                    .map(|i| (pos.line + line_count - i) % line_count)
+                    .find_map(|line| {
+                        self.line_matches(lines[line]).last().map(|&(start, end)| {
+                            Range::new(Position::new(line, start), end - start)
+                        })
+                    })
+            }
+        }
+    }
+
+    /**
+     * Byte ranges of every non-empty match in a line.
+     */
+    pub fn line_matches(&self, line: &str) -> Vec<(usize, usize)> {
+        line_matches(&self.regex, line)
+    }
+}
+
+pub fn line_matches(regex: &Regex, line: &str) -> Vec<(usize, usize)> {
+    regex
+        .find_iter(line)
+        .filter(|m| m.start() != m.end())
+        .map(|m| (m.start(), m.end()))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::text::PieceTableBuffer;
+
+    #[test]
+    fn test_find_forward_wraps() {
+        let text = PieceTableBuffer::new("foo bar\nbaz foo\n".to_string());
+        let search = Search::new("foo", Direction::Forward, false).unwrap();
+
+        let found = search
+            .find(&text, Position::new(0, 0), Direction::Forward)
+            .unwrap();
+        assert_eq!((found.start.line, found.start.col), (1, 4));
+
+        let found = search
+            .find(&text, Position::new(1, 4), Direction::Forward)
+            .unwrap();
+        assert_eq!((found.start.line, found.start.col), (0, 0));
+        assert_eq!(found.length, 3);
+    }
+
+    #[test]
+    fn test_find_backward() {
+        let text = PieceTableBuffer::new("foo bar\nbaz foo\n".to_string());
+        let search = Search::new("ba.", Direction::Backward, false).unwrap();
+
+        let found = search
+            .find(&text, Position::new(1, 4), Direction::Backward)
+            .unwrap();
+        assert_eq!((found.start.line, found.start.col), (1, 0));
+
+        let found = search
+            .find(&text, Position::new(1, 0), Direction::Backward)
+            .unwrap();
+        assert_eq!((found.start.line, found.start.col), (0, 4));
+    }
+}
@@ -1,77 +1,99 @@
+use super::prompt::Prompt;
 use super::State;
-use crate::buffer::Buffer;
 use crate::editor::Editor;
 use crate::event::Event;
-use crate::file::{load_file, write_file};
-use crate::text::{ArrayBuffer, TextBuffer};
-use std::error::Error;
+use crate::ex;
+use crate::ex::completion::{self, Completion};
+use crate::ex::ExResult;
 use termion::event::Key;
 
 pub struct CommandState {
-    pub buffer: Buffer<ArrayBuffer>,
+    pub prompt: Prompt,
+    // Set while cycling through tab completions
+    pub completion: Option<Completion>,
 }
 
 impl CommandState {
     pub fn new() -> Self {
         CommandState {
-            buffer: Buffer::new(Box::new(ArrayBuffer::new("".to_string()))),
+            prompt: Prompt::new(),
+            completion: None,
         }
     }
 
     pub fn handle_event(self, event: Event, editor: &mut Editor) -> Vec<State> {
         match event {
             Event::Key(key) => self.handle_key(key, editor),
+            _ => vec![State::Command(self)],
         }
     }
 
     fn handle_key(mut self, key: Key, editor: &mut Editor) -> Vec<State> {
         match key {
-            Key::Char('\n') => {
-                self.run_command(editor).unwrap();
-                return vec![];
+            Key::Char('\t') => {
+                self.complete(editor, true);
+                return vec![State::Command(self)];
             }
-            Key::Char(c) => {
-                self.buffer.insert(c);
+            Key::BackTab => {
+                self.complete(editor, false);
+                return vec![State::Command(self)];
+            }
+            _ => self.completion = None,
+        }
+
+        match key {
+            Key::Char('\n') => {
+                editor.command_history.push(self.prompt.text().as_str());
+                return match self.run_command(editor) {
+                    Ok(states) => states,
+                    Err(e) => {
+                        editor.messages.error(e.to_string().as_str());
+                        vec![]
+                    }
+                };
             }
             Key::Esc => {
                 return vec![];
             }
-            Key::Backspace => self.buffer.delete(),
-            Key::Left => self.buffer.move_cursor(self.buffer.prev()),
-            Key::Right => self.buffer.move_cursor(self.buffer.next()),
-            _ => (),
+            // Backspace on an empty command line leaves it
+            Key::Backspace if self.prompt.is_empty() => {
+                return vec![];
+            }
+            key => {
+                self.prompt
+                    .handle_key(key, &editor.command_history, &editor.registers);
+            }
         }
 
         vec![State::Command(self)]
     }
 
-    pub fn run_command(&mut self, editor: &mut Editor) -> Result<(), Box<dyn Error + 'static>> {
-        let text = self.buffer.text_buffer.to_string();
-        let parts = text.split_whitespace().collect::<Vec<&str>>();
-        let (&command, args) = parts.split_first().ok_or("Invalid command")?;
+    fn complete(&mut self, editor: &Editor, forward: bool) {
+        if self.completion.is_none() {
+            let text = self.prompt.text();
+            let before = &text[..self.prompt.cursor()];
+            self.completion = completion::complete(before, editor);
+        }
 
-        match command {
-            "q" => editor.running = false,
-            "w" => match args {
-                [] => {
-                    if let Some(filename) = &editor.filename {
-                        write_file(filename.as_str(), &editor.text_buffer)?;
-                    }
-                }
-                [filename] => {
-                    write_file(filename, &editor.text_buffer)?;
-                }
-                _ => (),
-            },
-            "edit" | "e" => match args {
-                [filename] => {
-                    editor.filename = Some(filename.to_string());
-                    editor.text_buffer = load_file(filename).unwrap();
-                }
-                _ => (),
-            },
-            _ => (),
+        let completion = match &mut self.completion {
+            Some(completion) => completion,
+            None => return,
         };
-        Ok(())
+        let start = completion.start;
+        let text = completion.step(forward).to_string();
+        self.prompt.replace_before_cursor(start, text.as_str());
+
+        // Nothing to cycle through with a single match
+        if completion.candidates.len() == 1 {
+            self.completion = None;
+        }
+    }
+
+    /**
+     * Run the command line. Returns any states the command pushes.
+     */
+    pub fn run_command(&mut self, editor: &mut Editor) -> ExResult {
+        let text = self.prompt.text();
+        ex::run(text.as_str(), editor)
     }
 }
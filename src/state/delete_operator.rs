@@ -1,32 +1,50 @@
 use super::State;
 use crate::editor::Editor;
 use crate::event::Event;
-use crate::text::TextBuffer;
-use crate::text_object::TextObject;
+use crate::register::Register;
 use termion::event::Key;
 
 #[derive(Clone, Debug)]
-pub struct DeleteOperatorState {}
+pub struct DeleteOperatorState {
+    pending: Option<char>,
+}
 
 impl DeleteOperatorState {
     pub fn new() -> Self {
-        DeleteOperatorState {}
+        DeleteOperatorState { pending: None }
     }
 
     pub fn handle_event(self, event: Event, editor: &mut Editor) -> Vec<State> {
         match event {
             Event::Key(key) => self.handle_key(key, editor),
+            _ => vec![State::DeleteOperator(self)],
         }
     }
 
-    fn handle_key(self, key: Key, editor: &mut Editor) -> Vec<State> {
+    fn handle_key(mut self, key: Key, editor: &mut Editor) -> Vec<State> {
+        if let Some(pending) = self.pending.take() {
+            // daf deletes the function around the cursor
+            if let ('a', Key::Char('f')) = (pending, key) {
+                match editor.text_buffer.function_object() {
+                    Some((start, end)) => {
+                        let removed = editor.text_buffer.delete_between(start, end);
+                        editor.registers.set(None, Register::new(removed, false));
+                    }
+                    None => editor.messages.error("No syntax tree node here"),
+                }
+            }
+            return vec![];
+        }
+
         match key {
+            Key::Char('a') => {
+                self.pending = Some('a');
+                return vec![State::DeleteOperator(self)];
+            }
             Key::Char('d') => {
                 let line = editor.text_buffer.cursor.line;
-                let text_object = TextObject::linewise(line, line);
-                let range = text_object.range(editor.text_buffer.text_buffer.as_ref());
-                let text = &mut editor.text_buffer.text_buffer;
-                text.as_mut().delete(range);
+                let removed = editor.text_buffer.delete_lines(line, line);
+                editor.registers.set(None, Register::new(removed, true));
             }
             _ => (),
         }
@@ -14,12 +14,26 @@ impl InsertState {
     pub fn handle_event(self, event: Event, editor: &mut Editor) -> Vec<State> {
         match event {
             Event::Key(key) => self.handle_key(key, editor),
+            Event::Paste(text) => {
+                // Inserted all at once, so the whole paste is one undo step
+                editor.text_buffer.insert_str(text.as_str());
+                vec![State::Insert(self)]
+            }
+            _ => vec![State::Insert(self)],
         }
     }
 
     fn handle_key(self, key: Key, editor: &mut Editor) -> Vec<State> {
         let buffer = &mut editor.text_buffer;
         match key {
+            // With expandtab, Tab inserts spaces up to the next tabstop
+            Key::Char('\t') if buffer.options.expandtab => {
+                let tabstop = buffer.options.tabstop;
+                let col = buffer.display_col(buffer.cursor());
+                for _ in 0..tabstop - col % tabstop {
+                    buffer.insert(' ');
+                }
+            }
             Key::Char(c) => {
                 buffer.insert(c);
             }
@@ -38,3 +52,33 @@ impl InsertState {
         vec![State::Insert(self)]
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::buffer::{Buffer, Position};
+    use crate::editor::Editor;
+    use crate::event::Event;
+    use crate::text::{PieceTableBuffer, TextBuffer};
+    use termion::event::Key;
+
+    #[test]
+    fn test_paste() {
+        let mut editor = Editor::new();
+        editor.text_buffer = Buffer::new(Box::new(PieceTableBuffer::new("ab\n".to_string())));
+        editor.handle_event(Event::Key(Key::Char('a')));
+        editor.handle_event(Event::Paste("x\nyz".to_string()));
+        assert_eq!(editor.text_buffer.text_buffer.to_string(), "ax\nyzb\n");
+        assert_eq!(editor.text_buffer.cursor(), Position::new(1, 2));
+
+        // A single undo takes the whole paste back out
+        editor.handle_event(Event::Key(Key::Esc));
+        editor.handle_event(Event::Key(Key::Char('u')));
+        assert_eq!(editor.text_buffer.text_buffer.to_string(), "ab\n");
+
+        // Outside Insert mode the paste is put, not run as keys
+        editor.text_buffer.move_cursor(Position::new(0, 1));
+        editor.handle_event(Event::Paste("dd".to_string()));
+        assert_eq!(editor.text_buffer.text_buffer.to_string(), "addb\n");
+        assert_eq!(editor.text_buffer.cursor(), Position::new(0, 2));
+    }
+}
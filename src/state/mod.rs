@@ -2,11 +2,19 @@ mod command;
 mod delete_operator;
 mod insert;
 mod normal;
+mod prompt;
+mod search;
+mod substitute;
+mod visual;
 
 pub use command::CommandState;
 pub use delete_operator::DeleteOperatorState;
 pub use insert::InsertState;
 pub use normal::NormalState;
+pub use prompt::Prompt;
+pub use search::SearchState;
+pub use substitute::SubstituteState;
+pub use visual::VisualState;
 
 use crate::editor::Editor;
 use crate::event::Event;
@@ -14,10 +22,13 @@ use crate::event::Event;
 pub enum State {
     Normal(NormalState),
     DeleteOperator(DeleteOperatorState),
+    Visual(VisualState),
 
     Insert(InsertState),
 
     Command(CommandState),
+    Search(SearchState),
+    Substitute(SubstituteState),
 }
 
 impl State {
@@ -25,8 +36,11 @@ impl State {
         match self {
             State::Normal(s) => s.handle_event(event, editor),
             State::Command(s) => s.handle_event(event, editor),
+            State::Search(s) => s.handle_event(event, editor),
+            State::Substitute(s) => s.handle_event(event, editor),
             State::Insert(s) => s.handle_event(event, editor),
             State::DeleteOperator(s) => s.handle_event(event, editor),
+            State::Visual(s) => s.handle_event(event, editor),
         }
     }
 }
@@ -1,23 +1,55 @@
-use super::{CommandState, DeleteOperatorState, InsertState, State};
+use super::{CommandState, DeleteOperatorState, InsertState, SearchState, State, VisualState};
+use crate::buffer::Position;
+use crate::cmdline_window::{self, CmdlineKind};
 use crate::editor::Editor;
 use crate::event::Event;
+use crate::ex::{self, ExError};
+use crate::scroll::ScrollTo;
+use crate::search::Direction;
+use crate::window::SplitDirection;
 use termion::event::Key;
 
+// Pending marker for Ctrl-w window commands
+const CTRL_W: char = '\u{17}';
+
 #[derive(Clone, Debug)]
-pub struct NormalState {}
+pub struct NormalState {
+    // The first key of a two key command, like m{a-z}
+    pending: Option<char>,
+}
 
 impl NormalState {
     pub fn new() -> Self {
-        NormalState {}
+        NormalState { pending: None }
+    }
+
+    /**
+     * Whether a key is waiting for the rest of its command
+     */
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
     }
 
     pub fn handle_event(self, event: Event, editor: &mut Editor) -> Vec<State> {
         match event {
             Event::Key(key) => self.handle_key(key, editor),
+            _ => vec![State::Normal(self)],
         }
     }
 
-    fn handle_key(self, key: Key, editor: &mut Editor) -> Vec<State> {
+    fn handle_key(mut self, key: Key, editor: &mut Editor) -> Vec<State> {
+        if let Some(pending) = self.pending.take() {
+            handle_pending(pending, key, editor);
+            return vec![State::Normal(self)];
+        }
+
+        // Enter in the command-line window runs the line under the cursor
+        if key == Key::Char('\n') && editor.cmdline_window.is_some() {
+            let mut states = vec![State::Normal(self)];
+            states.append(&mut cmdline_window::run(editor));
+            return states;
+        }
+
         let buffer = &mut editor.text_buffer;
         match key {
             // Motions
@@ -31,7 +63,7 @@ impl NormalState {
             // Insert mode commands
             Key::Char('i') => return self.push_insert(),
             Key::Char('a') => {
-                buffer.next();
+                buffer.move_cursor(buffer.next());
                 return self.push_insert();
             }
             Key::Char('o') => {
@@ -46,14 +78,67 @@ impl NormalState {
             // Command mode commands
             Key::Char(':') => return self.push_command(),
 
+            // Search
+            Key::Char('/') => return self.push_search(Direction::Forward, editor),
+            Key::Char('?') => return self.push_search(Direction::Backward, editor),
+            Key::Char('n') => repeat_search(editor, false),
+            Key::Char('N') => repeat_search(editor, true),
+
             // Change commands
             Key::Char('x') => buffer.delete(),
 
+            // Visual mode
+            Key::Char('v') => {
+                let cursor = buffer.cursor();
+                return self.push_state(State::Visual(VisualState::new(cursor)));
+            }
+
+            // ]f and [f move to the next and previous function
+            Key::Char(c @ ']') | Key::Char(c @ '[') => self.pending = Some(c),
+
             // Operators
             Key::Char('d') => {
                 return self.push_state(State::DeleteOperator(DeleteOperatorState::new()))
             }
 
+            // Marks
+            Key::Char(c @ 'm') | Key::Char(c @ '\'') | Key::Char(c @ '`') => self.pending = Some(c),
+
+            // Command-line window
+            Key::Char('q') => self.pending = Some('q'),
+
+            // Alternate buffer
+            Key::Ctrl('6') => {
+                let result = match editor.buffers.alternate {
+                    _ if editor.cmdline_window.is_some() => Err(ExError::CmdlineWindow),
+                    Some(id) => editor.switch_buffer(id),
+                    None => Err(ExError::NoAlternateFile),
+                };
+                if let Err(e) = result {
+                    editor.messages.error(e.to_string().as_str());
+                }
+            }
+
+            // Window commands
+            Key::Ctrl('w') => self.pending = Some(CTRL_W),
+
+            // Scrolling
+            Key::Ctrl('e') => editor.scroll_lines(1),
+            Key::Ctrl('y') => editor.scroll_lines(-1),
+            Key::Ctrl('d') => editor.scroll_half_page(true),
+            Key::Ctrl('u') => editor.scroll_half_page(false),
+            Key::Ctrl('f') => editor.scroll_page(true),
+            Key::Ctrl('b') => editor.scroll_page(false),
+
+            // zt, zz and zb scroll the cursor line to the top, center or bottom
+            Key::Char('z') => self.pending = Some('z'),
+
+            // gt and gT switch tab pages
+            Key::Char('g') => self.pending = Some('g'),
+
+            // ZZ writes if needed and quits, ZQ quits without writing
+            Key::Char('Z') => self.pending = Some('Z'),
+
             // Undo/redo
             Key::Char('u') => buffer.text_buffer.undo(),
 
@@ -73,4 +158,122 @@ impl NormalState {
     fn push_command(self) -> Vec<State> {
         self.push_state(State::Command(CommandState::new()))
     }
+
+    fn push_search(self, direction: Direction, editor: &Editor) -> Vec<State> {
+        self.push_state(State::Search(SearchState::new(direction, editor)))
+    }
+}
+
+fn handle_pending(pending: char, key: Key, editor: &mut Editor) {
+    let buffer = &mut editor.text_buffer;
+    match (pending, key) {
+        ('m', Key::Char(mark)) if mark.is_ascii_alphabetic() => {
+            let cursor = buffer.cursor();
+            buffer.marks.set(mark, cursor);
+        }
+        ('\'', Key::Char(mark)) => {
+            if let Some(pos) = buffer.marks.get(mark) {
+                buffer.move_cursor(Position::new(pos.line, 0));
+            }
+        }
+        ('`', Key::Char(mark)) => {
+            if let Some(pos) = buffer.marks.get(mark) {
+                buffer.move_cursor(pos);
+            }
+        }
+        (CTRL_W, Key::Char(c)) | (CTRL_W, Key::Ctrl(c)) => window_command(c, editor),
+        (']', Key::Char('f')) | ('[', Key::Char('f')) => {
+            match buffer.next_function(pending == ']') {
+                Some(pos) => buffer.move_cursor(pos),
+                None => editor.messages.error("No syntax tree node here"),
+            }
+        }
+        ('g', Key::Char('t')) => run_ex("tabnext", editor),
+        ('g', Key::Char('T')) => run_ex("tabprevious", editor),
+        ('z', Key::Char(c)) => {
+            let to = match c {
+                't' | '\n' => ScrollTo::Top,
+                'z' | '.' => ScrollTo::Center,
+                'b' | '-' => ScrollTo::Bottom,
+                _ => return,
+            };
+            editor.scroll_cursor_to(to);
+
+            // z<CR>, z. and z- also go to the first non-blank
+            if "\n.-".contains(c) {
+                let buffer = &mut editor.text_buffer;
+                buffer.move_cursor(buffer.first_non_blank());
+            }
+        }
+        ('Z', Key::Char('Z')) => run_ex("x", editor),
+        ('Z', Key::Char('Q')) => run_ex("q!", editor),
+        ('q', Key::Char(':')) => cmdline_window::open(CmdlineKind::Command, editor),
+        ('q', Key::Char(c @ '/')) | ('q', Key::Char(c @ '?')) => {
+            let direction = if c == '/' {
+                Direction::Forward
+            } else {
+                Direction::Backward
+            };
+            cmdline_window::open(CmdlineKind::Search(direction), editor)
+        }
+        _ => (),
+    }
+}
+
+/**
+ * The key after Ctrl-w
+ */
+fn window_command(c: char, editor: &mut Editor) {
+    if editor.cmdline_window.is_some() {
+        editor
+            .messages
+            .error(ExError::CmdlineWindow.to_string().as_str());
+        return;
+    }
+
+    match c {
+        's' => editor.split_window(SplitDirection::Horizontal),
+        'v' => editor.split_window(SplitDirection::Vertical),
+        'h' | 'j' | 'k' | 'l' => editor.move_to_window(c),
+        'c' => {
+            if let Err(e) = editor.close_window() {
+                editor.messages.error(e.to_string().as_str());
+            }
+        }
+        'o' => editor.only_window(),
+        '=' => editor.equalize_windows(),
+        _ => (),
+    }
+}
+
+fn run_ex(command: &str, editor: &mut Editor) {
+    if let Err(e) = ex::run(command, editor) {
+        editor.messages.error(e.to_string().as_str());
+    }
+}
+
+fn repeat_search(editor: &mut Editor, reverse: bool) {
+    let search = match &editor.search {
+        Some(search) => search,
+        None => {
+            editor.messages.error("No previous regular expression");
+            return;
+        }
+    };
+    let direction = if reverse {
+        search.direction.reverse()
+    } else {
+        search.direction
+    };
+
+    let buffer = &mut editor.text_buffer;
+    match search.find(buffer.text_buffer.as_ref(), buffer.cursor(), direction) {
+        Some(found) => buffer.move_cursor(found.start),
+        None => {
+            let e = ExError::PatternNotFound(search.pattern.clone());
+            editor.messages.error(e.to_string().as_str());
+            return;
+        }
+    }
+    editor.search_highlight = true;
 }
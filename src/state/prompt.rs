@@ -0,0 +1,159 @@
+use crate::buffer::{Buffer, Position};
+use crate::history::History;
+use crate::register::Registers;
+use crate::text::{ArrayBuffer, TextBuffer};
+use termion::event::Key;
+
+/**
+ * The single line editor shared by the : and / prompts. Handles cursor
+ * movement, deletion, history and inserting registers.
+ */
+pub struct Prompt {
+    pub buffer: Buffer<ArrayBuffer>,
+    history_idx: Option<usize>,
+    // The text typed before browsing history. Entries are filtered by it.
+    history_prefix: String,
+    pending_register: bool,
+}
+
+impl Prompt {
+    pub fn new() -> Self {
+        Prompt {
+            buffer: Buffer::new(Box::new(ArrayBuffer::new("".to_string()))),
+            history_idx: None,
+            history_prefix: String::new(),
+            pending_register: false,
+        }
+    }
+
+    pub fn text(&self) -> String {
+        self.buffer.text_buffer.to_string()
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.buffer.cursor().col
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.text_buffer.text.is_empty()
+    }
+
+    /**
+     * Replace the text and move the cursor to the end
+     */
+    pub fn set_text(&mut self, text: &str) {
+        self.buffer.text_buffer.text = text.to_string();
+        self.buffer.move_cursor(Position::new(0, text.len()));
+    }
+
+    /**
+     * Replace the text between start and the cursor
+     */
+    pub fn replace_before_cursor(&mut self, start: usize, replacement: &str) {
+        let cursor = self.cursor();
+        let text = &mut self.buffer.text_buffer.text;
+        text.replace_range(start..cursor, replacement);
+        self.buffer
+            .move_cursor(Position::new(0, start + replacement.len()));
+    }
+
+    fn insert_str(&mut self, text: &str) {
+        let cursor = self.cursor();
+        self.buffer.text_buffer.text.insert_str(cursor, text);
+        self.buffer
+            .move_cursor(Position::new(0, cursor + text.len()));
+    }
+
+    /**
+     * Handle a line editing key. Returns false if the key isn't one.
+     */
+    pub fn handle_key(&mut self, key: Key, history: &History, registers: &Registers) -> bool {
+        if self.pending_register {
+            self.pending_register = false;
+            if let Key::Char(name) = key {
+                if let Some(register) = registers.get(name) {
+                    let text = register.text.trim_end_matches('\n').to_string();
+                    self.insert_str(text.as_str());
+                }
+            }
+            return true;
+        }
+
+        match key {
+            Key::Up => self.history_prev(history),
+            Key::Down => self.history_next(history),
+            _ => {
+                self.history_idx = None;
+                self.history_prefix.clear();
+            }
+        }
+
+        let cursor = self.cursor();
+        match key {
+            Key::Up | Key::Down => (),
+            Key::Char('\n') | Key::Char('\t') => return false,
+            Key::Char(c) => self.buffer.insert(c),
+            Key::Backspace => self.buffer.delete(),
+            Key::Left => self.buffer.move_cursor(self.buffer.prev()),
+            Key::Right => self.buffer.move_cursor(self.buffer.next()),
+            Key::Home | Key::Ctrl('a') | Key::Ctrl('b') => {
+                self.buffer.move_cursor(Position::new(0, 0))
+            }
+            Key::End | Key::Ctrl('e') => self.buffer.move_cursor(self.buffer.end_line()),
+            Key::Ctrl('u') => self.replace_before_cursor(0, ""),
+            Key::Ctrl('w') => {
+                // Delete trailing whitespace, then the word or run of symbols
+                let before = &self.buffer.text_buffer.text[..cursor];
+                let trimmed = before.trim_end();
+                let is_word = |c: char| c.is_alphanumeric() || c == '_';
+                let start = match trimmed.chars().last() {
+                    Some(c) if is_word(c) => trimmed.trim_end_matches(is_word).len(),
+                    Some(_) => trimmed
+                        .trim_end_matches(|c: char| !is_word(c) && !c.is_whitespace())
+                        .len(),
+                    None => 0,
+                };
+                self.replace_before_cursor(start, "");
+            }
+            Key::Ctrl('r') => self.pending_register = true,
+            _ => return false,
+        }
+        true
+    }
+
+    fn history_prev(&mut self, history: &History) {
+        if self.history_idx.is_none() {
+            self.history_prefix = self.text();
+        }
+        if let Some(idx) = history.prev(self.history_idx, self.history_prefix.as_str()) {
+            self.history_idx = Some(idx);
+            self.set_text(history.entries()[idx].as_str());
+        }
+    }
+
+    fn history_next(&mut self, history: &History) {
+        let idx = match self.history_idx {
+            Some(idx) => idx,
+            None => return,
+        };
+
+        // Moving past the newest entry brings back what was typed
+        match history.next(idx, self.history_prefix.as_str()) {
+            Some(idx) => {
+                self.history_idx = Some(idx);
+                self.set_text(history.entries()[idx].as_str());
+            }
+            None => {
+                self.history_idx = None;
+                let prefix = std::mem::take(&mut self.history_prefix);
+                self.set_text(prefix.as_str());
+            }
+        }
+    }
+}
+
+impl Default for Prompt {
+    fn default() -> Self {
+        Prompt::new()
+    }
+}
@@ -0,0 +1,143 @@
+use super::prompt::Prompt;
+use super::State;
+use crate::buffer::Position;
+use crate::editor::Editor;
+use crate::event::Event;
+use crate::ex::ExError;
+use crate::search::{Direction, Search};
+use crate::text::Range;
+use termion::event::Key;
+
+/**
+ * The / and ? prompt. With incsearch enabled, the cursor jumps to the
+ * first match while typing and returns to origin if the search is aborted.
+ */
+pub struct SearchState {
+    pub prompt: Prompt,
+    pub direction: Direction,
+    pub preview: Option<(Search, Range)>,
+    origin: Position,
+    origin_offset: usize,
+}
+
+impl SearchState {
+    pub fn new(direction: Direction, editor: &Editor) -> Self {
+        SearchState {
+            prompt: Prompt::new(),
+            direction,
+            preview: None,
+            origin: editor.text_buffer.cursor(),
+            origin_offset: editor.text_window_state.offset,
+        }
+    }
+
+    pub fn handle_event(self, event: Event, editor: &mut Editor) -> Vec<State> {
+        match event {
+            Event::Key(key) => self.handle_key(key, editor),
+            _ => vec![State::Search(self)],
+        }
+    }
+
+    fn handle_key(mut self, key: Key, editor: &mut Editor) -> Vec<State> {
+        match key {
+            Key::Char('\n') => {
+                editor.search_history.push(self.prompt.text().as_str());
+                self.run_search(editor);
+                return vec![];
+            }
+            Key::Esc => {
+                self.restore(editor);
+                return vec![];
+            }
+            // Backspace on an empty prompt aborts the search
+            Key::Backspace if self.prompt.is_empty() => {
+                self.restore(editor);
+                return vec![];
+            }
+            key => {
+                let before = self.prompt.text();
+                self.prompt
+                    .handle_key(key, &editor.search_history, &editor.registers);
+                if self.prompt.text() != before {
+                    self.update_preview(editor);
+                }
+            }
+        }
+
+        vec![State::Search(self)]
+    }
+
+    fn update_preview(&mut self, editor: &mut Editor) {
+        if !editor.options.incsearch {
+            return;
+        }
+
+        let pattern = self.prompt.text();
+        let ignore_case = editor.options.ignore_case(pattern.as_str());
+        self.preview = Search::new(pattern.as_str(), self.direction, ignore_case)
+            .ok()
+            .filter(|_| !pattern.is_empty())
+            .and_then(|search| {
+                let text = editor.text_buffer.text_buffer.as_ref();
+                let found = search.find(text, self.origin, self.direction)?;
+                Some((search, found))
+            });
+
+        match &self.preview {
+            Some((_, found)) => editor.text_buffer.move_cursor(found.start),
+            None => {
+                editor.text_buffer.move_cursor(self.origin);
+                editor.text_window_state.offset = self.origin_offset;
+            }
+        }
+    }
+
+    fn restore(&self, editor: &mut Editor) {
+        editor.text_buffer.move_cursor(self.origin);
+        editor.text_window_state.offset = self.origin_offset;
+    }
+
+    fn run_search(&self, editor: &mut Editor) {
+        let pattern = self.prompt.text();
+
+        // An empty pattern repeats the last search in the new direction
+        let search = if pattern.is_empty() {
+            editor.search.as_ref().map(|search| Search {
+                direction: self.direction,
+                ..search.clone()
+            })
+        } else {
+            let ignore_case = editor.options.ignore_case(pattern.as_str());
+            match Search::new(pattern.as_str(), self.direction, ignore_case) {
+                Ok(search) => Some(search),
+                Err(e) => {
+                    editor.messages.error(ExError::from(e).to_string().as_str());
+                    self.restore(editor);
+                    return;
+                }
+            }
+        };
+
+        let search = match search {
+            Some(search) => search,
+            None => {
+                editor.messages.error("No previous regular expression");
+                self.restore(editor);
+                return;
+            }
+        };
+
+        let text = editor.text_buffer.text_buffer.as_ref();
+        match search.find(text, self.origin, self.direction) {
+            Some(found) => editor.text_buffer.move_cursor(found.start),
+            None => {
+                let e = ExError::PatternNotFound(search.pattern.clone());
+                editor.messages.error(e.to_string().as_str());
+                self.restore(editor);
+            }
+        }
+
+        editor.search = Some(search);
+        editor.search_highlight = true;
+    }
+}
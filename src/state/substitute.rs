@@ -0,0 +1,165 @@
+use super::State;
+use crate::buffer::Position;
+use crate::editor::Editor;
+use crate::event::Event;
+use crate::ex::substitute::{replace_match, Match, Substitute};
+use crate::ex::LineRange;
+use crate::text::{Range, UndoGroup};
+use termion::event::Key;
+
+/**
+ * Confirm mode for :s///c. Highlights each match in turn and waits for
+ * y/n/a/q/l. The replacements made are undone as a single step.
+ */
+pub struct SubstituteState {
+    substitute: Substitute,
+    line: usize,
+    end: usize,
+    current: Match,
+    // Where to look for the next match in the current line
+    next_col: usize,
+    last_changed: Option<usize>,
+    undo_group: UndoGroup,
+}
+
+impl SubstituteState {
+    /**
+     * Jump to the first match in the range. Returns None if there isn't one.
+     */
+    pub fn start(substitute: Substitute, range: LineRange, editor: &mut Editor) -> Option<Self> {
+        let text = editor.text_buffer.text_buffer.as_mut();
+        let (line, current) = substitute.next_match(text, range.start, 0, range.end)?;
+        let undo_group = text.start_undo_group();
+
+        let state = SubstituteState {
+            substitute,
+            line,
+            end: range.end,
+            next_col: next_col(&current),
+            current,
+            last_changed: None,
+            undo_group,
+        };
+        editor.text_buffer.move_cursor(state.current_position());
+        Some(state)
+    }
+
+    /**
+     * The match waiting for confirmation
+     */
+    pub fn current(&self) -> Range {
+        Range::new(
+            self.current_position(),
+            self.current.end - self.current.start,
+        )
+    }
+
+    pub fn replacement(&self) -> &str {
+        self.current.replacement.as_str()
+    }
+
+    fn current_position(&self) -> Position {
+        Position::new(self.line, self.current.start)
+    }
+
+    pub fn handle_event(self, event: Event, editor: &mut Editor) -> Vec<State> {
+        match event {
+            Event::Key(key) => self.handle_key(key, editor),
+            _ => vec![State::Substitute(self)],
+        }
+    }
+
+    fn handle_key(mut self, key: Key, editor: &mut Editor) -> Vec<State> {
+        let more = match key {
+            Key::Char('y') => {
+                self.replace(editor);
+                self.advance(editor)
+            }
+            Key::Char('n') => self.advance(editor),
+            Key::Char('a') => {
+                loop {
+                    self.replace(editor);
+                    if !self.advance(editor) {
+                        break;
+                    }
+                }
+                false
+            }
+            Key::Char('l') => {
+                self.replace(editor);
+                false
+            }
+            Key::Char('q') | Key::Esc => false,
+            _ => true,
+        };
+
+        if more {
+            return vec![State::Substitute(self)];
+        }
+        self.finish(editor);
+        vec![]
+    }
+
+    fn replace(&mut self, editor: &mut Editor) {
+        let text = editor.text_buffer.text_buffer.as_mut();
+        replace_match(text, self.line, &self.current);
+
+        // The replacement may have split the line, so continue after it
+        let replacement = self.current.replacement.as_str();
+        let added = replacement.matches('\n').count();
+        let end = match replacement.rfind('\n') {
+            Some(idx) => replacement.len() - idx - 1,
+            None => self.current.start + replacement.len(),
+        };
+        // Step past empty matches so they aren't replaced again
+        self.next_col = end + (self.current.start == self.current.end) as usize;
+        self.line += added;
+        self.end += added;
+        self.last_changed = Some(self.line);
+    }
+
+    /**
+     * Move to the next match. Returns false once there are no more.
+     */
+    fn advance(&mut self, editor: &mut Editor) -> bool {
+        let (line, col) = if self.substitute.flags.global {
+            (self.line, self.next_col)
+        } else {
+            (self.line + 1, 0)
+        };
+        if line > self.end {
+            return false;
+        }
+
+        let text = editor.text_buffer.text_buffer.as_ref();
+        match self.substitute.next_match(text, line, col, self.end) {
+            Some((line, found)) => {
+                self.line = line;
+                self.next_col = next_col(&found);
+                self.current = found;
+                editor.text_buffer.move_cursor(self.current_position());
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn finish(self, editor: &mut Editor) {
+        let text = editor.text_buffer.text_buffer.as_mut();
+        text.end_undo_group(self.undo_group);
+
+        let line = self.last_changed.unwrap_or(self.line);
+        editor.text_buffer.move_cursor(Position::new(line, 0));
+    }
+}
+
+/**
+ * Where to continue searching if the match is skipped
+ */
+fn next_col(m: &Match) -> usize {
+    if m.start == m.end {
+        m.end + 1
+    } else {
+        m.end
+    }
+}
@@ -0,0 +1,219 @@
+use super::{CommandState, State};
+use crate::buffer::{Buffer, Position};
+use crate::editor::Editor;
+use crate::event::Event;
+use crate::register::Register;
+use crate::text::PieceTableBuffer;
+use termion::event::Key;
+
+/**
+ * Charwise visual mode. The selection runs from the anchor to the cursor,
+ * both included.
+ */
+#[derive(Clone, Debug)]
+pub struct VisualState {
+    pub anchor: Position,
+    pending: Option<char>,
+
+    // Selections before each syntax node expansion, to shrink back to
+    expanded: Vec<(Position, Position)>,
+}
+
+impl VisualState {
+    pub fn new(anchor: Position) -> Self {
+        VisualState {
+            anchor,
+            pending: None,
+            expanded: vec![],
+        }
+    }
+
+    /**
+     * The selection with the cursor at the given position, start first
+     */
+    pub fn selection(&self, cursor: Position) -> (Position, Position) {
+        if (self.anchor.line, self.anchor.col) <= (cursor.line, cursor.col) {
+            (self.anchor, cursor)
+        } else {
+            (cursor, self.anchor)
+        }
+    }
+
+    /**
+     * Remember the selection in the '< and '> marks, as leaving visual
+     * mode does
+     */
+    pub fn set_marks(&self, buffer: &mut Buffer<PieceTableBuffer>) {
+        let (start, end) = self.selection(buffer.cursor());
+        buffer.marks.set('<', start);
+        buffer.marks.set('>', end);
+    }
+
+    /**
+     * Whether a key is waiting for the rest of its command
+     */
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    pub fn handle_event(self, event: Event, editor: &mut Editor) -> Vec<State> {
+        match event {
+            Event::Key(key) => self.handle_key(key, editor),
+            _ => vec![State::Visual(self)],
+        }
+    }
+
+    fn handle_key(mut self, key: Key, editor: &mut Editor) -> Vec<State> {
+        if let Some(pending) = self.pending.take() {
+            if let ('a', Key::Char('f')) = (pending, key) {
+                let function = editor.text_buffer.function_object();
+                self.select(function, editor);
+            }
+            return vec![State::Visual(self)];
+        }
+
+        let buffer = &mut editor.text_buffer;
+        let cursor = buffer.cursor();
+        match key {
+            Key::Esc | Key::Char('v') => {
+                self.set_marks(buffer);
+                return vec![];
+            }
+
+            // : works on the selected lines
+            Key::Char(':') => {
+                self.set_marks(buffer);
+                let mut command = CommandState::new();
+                command.prompt.set_text("'<,'>");
+                return vec![State::Command(command)];
+            }
+
+            Key::Left | Key::Char('h') => buffer.move_cursor(buffer.prev()),
+            Key::Right | Key::Char('l') => buffer.move_cursor(buffer.next()),
+            Key::Up | Key::Char('k') => buffer.move_cursor(buffer.prev_line()),
+            Key::Down | Key::Char('j') => buffer.move_cursor(buffer.next_line()),
+            Key::Char('0') => buffer.move_cursor(buffer.start_line()),
+            Key::Char('$') => buffer.move_cursor(buffer.end_line()),
+
+            // Move to the other end of the selection
+            Key::Char('o') => {
+                buffer.move_cursor(self.anchor);
+                self.anchor = cursor;
+            }
+
+            Key::Char('d') | Key::Char('x') => {
+                self.set_marks(buffer);
+                let (start, end) = self.selection(cursor);
+                let removed = buffer.delete_between(start, end);
+                editor.registers.set(None, Register::new(removed, false));
+                return vec![];
+            }
+            Key::Char('y') => {
+                self.set_marks(buffer);
+                let (start, end) = self.selection(cursor);
+                let text = buffer.text_between(start, end);
+                buffer.move_cursor(start);
+                editor.registers.set(None, Register::new(text, false));
+                return vec![];
+            }
+
+            // af selects the function around the cursor
+            Key::Char('a') => self.pending = Some('a'),
+
+            // + grows the selection to the enclosing syntax node, - undoes that
+            Key::Char('+') => {
+                let (start, end) = self.selection(cursor);
+                let node = buffer.expand_selection(start, end);
+                self.select(node, editor);
+            }
+            Key::Char('-') => {
+                if let Some((anchor, cursor)) = self.expanded.pop() {
+                    self.anchor = anchor;
+                    buffer.move_cursor(cursor);
+                }
+            }
+
+            _ => (),
+        }
+        vec![State::Visual(self)]
+    }
+
+    fn select(&mut self, node: Option<(Position, Position)>, editor: &mut Editor) {
+        let (start, end) = match node {
+            Some(node) => node,
+            None => {
+                editor.messages.error("No syntax tree node here");
+                return;
+            }
+        };
+
+        let buffer = &mut editor.text_buffer;
+        self.expanded.push((self.anchor, buffer.cursor()));
+        self.anchor = start;
+        buffer.move_cursor(end);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::buffer::Buffer;
+    use crate::register::UNNAMED;
+    use crate::text::{PieceTableBuffer, TextBuffer};
+
+    #[test]
+    fn test_visual_delete() {
+        let mut editor = Editor::new();
+        let text = "one two\nthree\n".to_string();
+        editor.text_buffer = Buffer::new(Box::new(PieceTableBuffer::new(text)));
+        editor.text_buffer.move_cursor(Position::new(0, 4));
+
+        let mut states = vec![State::Visual(VisualState::new(Position::new(0, 4)))];
+        for c in "jhd".chars() {
+            let state = states.pop().unwrap();
+            states = state.handle_event(Event::Key(Key::Char(c)), &mut editor);
+        }
+
+        assert!(states.is_empty());
+        assert_eq!(editor.text_buffer.text_buffer.to_string(), "one e\n");
+        assert_eq!(editor.registers.get(UNNAMED).unwrap().text, "two\nthre");
+
+        // Columns are bytes, so multibyte chars go whole
+        editor.text_buffer = Buffer::new(Box::new(PieceTableBuffer::new("héllo\n".to_string())));
+        let mut states = vec![State::Visual(VisualState::new(Position::new(0, 0)))];
+        for c in "ld".chars() {
+            let state = states.pop().unwrap();
+            states = state.handle_event(Event::Key(Key::Char(c)), &mut editor);
+        }
+        assert_eq!(editor.text_buffer.text_buffer.to_string(), "llo\n");
+        assert_eq!(editor.registers.get(UNNAMED).unwrap().text, "hé");
+    }
+
+    #[test]
+    fn test_visual_marks() {
+        let mut editor = Editor::new();
+        let text = "a\nb\nc\nd\n".to_string();
+        editor.text_buffer = Buffer::new(Box::new(PieceTableBuffer::new(text)));
+        editor.text_buffer.move_cursor(Position::new(1, 0));
+
+        // : runs on the selected lines
+        for c in "vj:d\n".chars() {
+            editor.handle_event(Event::Key(Key::Char(c)));
+        }
+        assert_eq!(editor.mode(), "NORMAL");
+        assert_eq!(editor.text_buffer.text_buffer.to_string(), "a\nd\n");
+
+        let marks = &editor.text_buffer.marks;
+        assert_eq!(marks.get('<'), Some(Position::new(1, 0)));
+        assert_eq!(marks.get('>'), Some(Position::new(2, 0)));
+
+        // Leaving with Esc sets them too, start first
+        editor.text_buffer.move_cursor(Position::new(1, 0));
+        for key in [Key::Char('v'), Key::Char('k'), Key::Esc].iter() {
+            editor.handle_event(Event::Key(*key));
+        }
+        let marks = &editor.text_buffer.marks;
+        assert_eq!(marks.get('<'), Some(Position::new(0, 0)));
+        assert_eq!(marks.get('>'), Some(Position::new(1, 0)));
+    }
+}
@@ -0,0 +1,114 @@
+use super::{HighlightGroup, LineState, Scanner};
+
+pub fn tokenize(s: &mut Scanner, state: LineState) -> LineState {
+    let trimmed = s.rest().trim_start();
+    let fence = trimmed.starts_with("```") || trimmed.starts_with("~~~");
+
+    // Code blocks are highlighted as a whole, up to the closing fence
+    if state == LineState::CodeBlock || fence {
+        s.skip_to_end();
+        s.token(0, HighlightGroup::String);
+        return match (state, fence) {
+            (LineState::CodeBlock, true) => LineState::Normal,
+            _ => LineState::CodeBlock,
+        };
+    }
+
+    let hashes = trimmed.chars().take_while(|&c| c == '#').count();
+    if (1..=6).contains(&hashes) && (trimmed.len() == hashes || trimmed[hashes..].starts_with(' '))
+    {
+        s.skip_to_end();
+        s.token(0, HighlightGroup::Title);
+        return LineState::Normal;
+    }
+    if trimmed.starts_with('>') {
+        s.skip_to_end();
+        s.token(0, HighlightGroup::Comment);
+        return LineState::Normal;
+    }
+
+    // List markers
+    s.eat_while(char::is_whitespace);
+    let start = s.pos();
+    let bullet = s.eat("- ") || s.eat("* ") || s.eat("+ ");
+    if bullet || (!s.eat_while(|c| c.is_ascii_digit()).is_empty() && s.eat(". ")) {
+        s.token(start, HighlightGroup::Special);
+    }
+
+    while let Some(c) = s.peek() {
+        let found = match c {
+            '`' => s.delimited("`", "`", HighlightGroup::String),
+            '*' => {
+                s.delimited("**", "**", HighlightGroup::Bold)
+                    || s.delimited("*", "*", HighlightGroup::Italic)
+            }
+            // Underscores inside words, like snake_case, aren't emphasis
+            '_' if !s.prev().is_some_and(char::is_alphanumeric) => {
+                s.delimited("__", "__", HighlightGroup::Bold)
+                    || s.delimited("_", "_", HighlightGroup::Italic)
+            }
+            '[' => link(s),
+            '\\' => {
+                s.bump();
+                false
+            }
+            _ => false,
+        };
+        if !found {
+            s.bump();
+        }
+    }
+    LineState::Normal
+}
+
+/**
+ * [text](url)
+ */
+fn link(s: &mut Scanner) -> bool {
+    let rest = s.rest();
+    let end = rest
+        .find("](")
+        .and_then(|text_end| Some(text_end + rest[text_end..].find(')')? + 1));
+    match end {
+        Some(end) => {
+            let start = s.pos();
+            s.advance(end);
+            s.token(start, HighlightGroup::Underlined);
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::Grammar;
+    use super::*;
+
+    #[test]
+    fn test_markdown() {
+        let grammar = Grammar::for_file("README.md").unwrap();
+        let line = "- Use `cargo` for **builds**, see [docs](http://x) or my_var";
+        let (tokens, _) = grammar.tokenize(line, LineState::Normal);
+        let groups = tokens
+            .iter()
+            .map(|token| (&line[token.start..token.end], token.group))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            groups,
+            vec![
+                ("- ", HighlightGroup::Special),
+                ("`cargo`", HighlightGroup::String),
+                ("**builds**", HighlightGroup::Bold),
+                ("[docs](http://x)", HighlightGroup::Underlined),
+            ]
+        );
+
+        let (_, state) = grammar.tokenize("```rust", LineState::Normal);
+        assert_eq!(state, LineState::CodeBlock);
+        let (tokens, state) = grammar.tokenize("# not a heading", state);
+        assert_eq!(tokens[0].group, HighlightGroup::String);
+        let (_, state) = grammar.tokenize("```", state);
+        assert_eq!(state, LineState::Normal);
+    }
+}
@@ -0,0 +1,485 @@
+mod markdown;
+mod rust;
+mod shell;
+mod toml;
+#[cfg(feature = "tree-sitter")]
+mod tree;
+
+use crate::buffer::Position;
+use crate::text::{PieceTableBuffer, TextBuffer};
+use std::fmt;
+use std::path::Path;
+
+/**
+ * What a piece of text is, as far as highlighting goes. Grammars tokenize
+ * into these, and the color scheme gives each one a style.
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HighlightGroup {
+    Comment,
+    Constant,
+    String,
+    Number,
+    Boolean,
+    Identifier,
+    Function,
+    Keyword,
+    Type,
+    PreProc,
+    Special,
+    Title,
+    Underlined,
+    Bold,
+    Italic,
+}
+
+impl HighlightGroup {
+    /**
+     * The group's name in color schemes
+     */
+    pub fn name(&self) -> &'static str {
+        match self {
+            HighlightGroup::Comment => "Comment",
+            HighlightGroup::Constant => "Constant",
+            HighlightGroup::String => "String",
+            HighlightGroup::Number => "Number",
+            HighlightGroup::Boolean => "Boolean",
+            HighlightGroup::Identifier => "Identifier",
+            HighlightGroup::Function => "Function",
+            HighlightGroup::Keyword => "Keyword",
+            HighlightGroup::Type => "Type",
+            HighlightGroup::PreProc => "PreProc",
+            HighlightGroup::Special => "Special",
+            HighlightGroup::Title => "Title",
+            HighlightGroup::Underlined => "Underlined",
+            HighlightGroup::Bold => "Bold",
+            HighlightGroup::Italic => "Italic",
+        }
+    }
+}
+
+/**
+ * A highlighted byte range within a line
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Token {
+    pub start: usize,
+    pub end: usize,
+    pub group: HighlightGroup,
+}
+
+/**
+ * Where a line leaves off, for constructs that span lines. The next line
+ * is tokenized starting from it.
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineState {
+    Normal,
+    // The depth of nested block comments
+    Comment(usize),
+    // Strings ending with the delimiter, with or without escapes
+    String(&'static str),
+    RawString(&'static str),
+    // A fenced code block in Markdown
+    CodeBlock,
+}
+
+/**
+ * A language's tokenizer, and the files it applies to
+ */
+pub struct Grammar {
+    pub name: &'static str,
+    extensions: &'static [&'static str],
+    filenames: &'static [&'static str],
+    tokenize: fn(&mut Scanner, LineState) -> LineState,
+}
+
+impl fmt::Debug for Grammar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Grammar({})", self.name)
+    }
+}
+
+static GRAMMARS: &[Grammar] = &[
+    Grammar {
+        name: "rust",
+        extensions: &["rs"],
+        filenames: &[],
+        tokenize: rust::tokenize,
+    },
+    Grammar {
+        name: "toml",
+        extensions: &["toml"],
+        filenames: &["Cargo.lock"],
+        tokenize: toml::tokenize,
+    },
+    Grammar {
+        name: "markdown",
+        extensions: &["md", "markdown"],
+        filenames: &[],
+        tokenize: markdown::tokenize,
+    },
+    Grammar {
+        name: "sh",
+        extensions: &["sh", "bash", "zsh"],
+        filenames: &[".bashrc", ".bash_profile", ".profile", ".zshrc"],
+        tokenize: shell::tokenize,
+    },
+];
+
+impl Grammar {
+    pub fn for_file(filename: &str) -> Option<&'static Grammar> {
+        let path = Path::new(filename);
+        let name = path.file_name()?.to_str()?;
+        let extension = path.extension().and_then(|e| e.to_str());
+        GRAMMARS.iter().find(|grammar| {
+            grammar.filenames.contains(&name)
+                || extension.is_some_and(|e| grammar.extensions.contains(&e))
+        })
+    }
+
+    pub fn tokenize(&self, line: &str, state: LineState) -> (Vec<Token>, LineState) {
+        let mut scanner = Scanner::new(line);
+        let state = (self.tokenize)(&mut scanner, state);
+        (scanner.tokens, state)
+    }
+}
+
+/**
+ * The tokens of a buffer's lines, highlighted lazily from the top down to
+ * the last line drawn. Each line's end state is kept, so after an edit
+ * only the lines from the first changed one on need tokenizing again.
+ * With tree-sitter, the tokens come from a parse tree instead.
+ */
+#[derive(Debug, Clone)]
+pub struct Highlighter {
+    grammar: &'static Grammar,
+    lines: Vec<(Vec<Token>, LineState)>,
+    #[cfg(feature = "tree-sitter")]
+    tree: Option<tree::SyntaxTree>,
+}
+
+impl Highlighter {
+    pub fn new(grammar: &'static Grammar) -> Self {
+        Highlighter {
+            grammar,
+            lines: vec![],
+            #[cfg(feature = "tree-sitter")]
+            tree: tree::SyntaxTree::new(grammar.name),
+        }
+    }
+
+    pub fn for_file(filename: &str) -> Option<Self> {
+        Grammar::for_file(filename).map(Highlighter::new)
+    }
+
+    pub fn grammar(&self) -> &'static Grammar {
+        self.grammar
+    }
+
+    /**
+     * Forget the lines from an edited one on
+     */
+    pub fn invalidate(&mut self, line: usize) {
+        self.lines.truncate(line);
+    }
+
+    /**
+     * Tokenize lines up to and including `last`, starting over from the
+     * first line edited since the last update
+     */
+    pub fn update(&mut self, text: &mut PieceTableBuffer, last: usize) {
+        if let Some(line) = text.changed_line.take() {
+            self.invalidate(line);
+        }
+
+        #[cfg(feature = "tree-sitter")]
+        {
+            if let Some(tree) = &mut self.tree {
+                // Not invalidate, as the tree still borrows self
+                if let Some(line) = tree.sync(text) {
+                    self.lines.truncate(line);
+                }
+                if self.lines.len() <= last {
+                    let tokens = tree.highlight(self.lines.len()..last + 1);
+                    self.lines
+                        .extend(tokens.into_iter().map(|tokens| (tokens, LineState::Normal)));
+                }
+                return;
+            }
+        }
+
+        while self.lines.len() <= last {
+            let state = self
+                .lines
+                .last()
+                .map_or(LineState::Normal, |(_, state)| *state);
+            let line = text.line(self.lines.len());
+            self.lines.push(self.grammar.tokenize(line.as_str(), state));
+        }
+    }
+
+    pub fn tokens(&self, line: usize) -> &[Token] {
+        self.lines
+            .get(line)
+            .map_or(&[], |(tokens, _)| tokens.as_slice())
+    }
+}
+
+/**
+ * Syntax nodes, for text objects and motions. Positions are inclusive.
+ */
+#[cfg(feature = "tree-sitter")]
+impl Highlighter {
+    pub fn function_around(&self, pos: Position) -> Option<(Position, Position)> {
+        self.tree.as_ref()?.function_around(pos)
+    }
+
+    pub fn expand(&self, start: Position, end: Position) -> Option<(Position, Position)> {
+        self.tree.as_ref()?.expand(start, end)
+    }
+
+    pub fn next_function(&self, pos: Position, forward: bool) -> Option<Position> {
+        self.tree.as_ref()?.next_function(pos, forward)
+    }
+}
+
+/**
+ * Without tree-sitter there are no syntax nodes
+ */
+#[cfg(not(feature = "tree-sitter"))]
+impl Highlighter {
+    pub fn function_around(&self, _pos: Position) -> Option<(Position, Position)> {
+        None
+    }
+
+    pub fn expand(&self, _start: Position, _end: Position) -> Option<(Position, Position)> {
+        None
+    }
+
+    pub fn next_function(&self, _pos: Position, _forward: bool) -> Option<Position> {
+        None
+    }
+}
+
+/**
+ * A cursor over a line for the grammars, collecting tokens as it goes
+ */
+pub struct Scanner<'a> {
+    line: &'a str,
+    pos: usize,
+    tokens: Vec<Token>,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(line: &'a str) -> Self {
+        Scanner {
+            line,
+            pos: 0,
+            tokens: vec![],
+        }
+    }
+
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    pub fn rest(&self) -> &'a str {
+        &self.line[self.pos..]
+    }
+
+    pub fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    pub fn peek_nth(&self, n: usize) -> Option<char> {
+        self.rest().chars().nth(n)
+    }
+
+    pub fn prev(&self) -> Option<char> {
+        self.line[..self.pos].chars().next_back()
+    }
+
+    /**
+     * Whether only whitespace comes before the current position
+     */
+    pub fn at_line_start(&self) -> bool {
+        self.line[..self.pos].trim().is_empty()
+    }
+
+    pub fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    pub fn advance(&mut self, len: usize) {
+        self.pos = (self.pos + len).min(self.line.len());
+    }
+
+    pub fn skip_to_end(&mut self) {
+        self.pos = self.line.len();
+    }
+
+    pub fn eat(&mut self, s: &str) -> bool {
+        if self.rest().starts_with(s) {
+            self.pos += s.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn eat_while<F: Fn(char) -> bool>(&mut self, f: F) -> &'a str {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if !f(c) {
+                break;
+            }
+            self.pos += c.len_utf8();
+        }
+        &self.line[start..self.pos]
+    }
+
+    /**
+     * Highlight from `start` to the current position
+     */
+    pub fn token(&mut self, start: usize, group: HighlightGroup) {
+        if self.pos > start {
+            self.tokens.push(Token {
+                start,
+                end: self.pos,
+                group,
+            });
+        }
+    }
+
+    /**
+     * Highlight text between delimiters on this line, like `code`. Nothing
+     * is consumed if it isn't closed.
+     */
+    pub fn delimited(&mut self, open: &str, close: &str, group: HighlightGroup) -> bool {
+        let start = self.pos;
+        let inner = match self.rest().strip_prefix(open) {
+            Some(inner) => inner,
+            None => return false,
+        };
+        match inner.find(close) {
+            Some(end) if end > 0 => {
+                self.advance(open.len() + end + close.len());
+                self.token(start, group);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /**
+     * Scan the rest of a string started at `start`, returning the state
+     * for the next line
+     */
+    pub fn string(&mut self, start: usize, close: &'static str, escapes: bool) -> LineState {
+        while self.peek().is_some() {
+            if escapes && self.eat("\\") {
+                self.bump();
+            } else if self.eat(close) {
+                self.token(start, HighlightGroup::String);
+                return LineState::Normal;
+            } else {
+                self.bump();
+            }
+        }
+        self.token(start, HighlightGroup::String);
+        if escapes {
+            LineState::String(close)
+        } else {
+            LineState::RawString(close)
+        }
+    }
+
+    /**
+     * Scan the rest of a block comment, counting nested openers if the
+     * language allows them
+     */
+    pub fn block_comment(
+        &mut self,
+        start: usize,
+        depth: usize,
+        delimiters: (&str, &str),
+        nested: bool,
+    ) -> LineState {
+        let (open, close) = delimiters;
+        let mut depth = depth;
+        while self.peek().is_some() {
+            if nested && self.eat(open) {
+                depth += 1;
+            } else if self.eat(close) {
+                depth -= 1;
+                if depth == 0 {
+                    self.token(start, HighlightGroup::Comment);
+                    return LineState::Normal;
+                }
+            } else {
+                self.bump();
+            }
+        }
+        self.token(start, HighlightGroup::Comment);
+        LineState::Comment(depth)
+    }
+
+    /**
+     * Digits, with any suffix, exponent or fraction
+     */
+    pub fn number(&mut self) {
+        let start = self.pos;
+        loop {
+            self.eat_while(|c| c.is_ascii_alphanumeric() || c == '_');
+            match (self.peek(), self.peek_nth(1)) {
+                (Some('.'), Some(c)) if c.is_ascii_digit() => self.advance(1),
+                _ => break,
+            }
+        }
+        self.token(start, HighlightGroup::Number);
+    }
+}
+
+pub fn is_ident_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+pub fn is_ident(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_incremental_update() {
+        let mut text = PieceTableBuffer::new("let a = 1; /* one\ntwo */ fn b() {}\nc".to_string());
+        let mut highlighter = Highlighter::new(&GRAMMARS[0]);
+        // Test the Rust regex grammar even with tree-sitter enabled
+        #[cfg(feature = "tree-sitter")]
+        {
+            highlighter.tree = None;
+        }
+        highlighter.update(&mut text, 2);
+        assert_eq!(highlighter.lines[0].1, LineState::Comment(1));
+        assert_eq!(
+            highlighter.tokens(1)[0],
+            Token {
+                start: 0,
+                end: 6,
+                group: HighlightGroup::Comment
+            }
+        );
+
+        // Closing the comment early changes how the following lines look
+        text.insert_str(Position::new(0, 17), " */");
+        highlighter.update(&mut text, 1);
+        assert_eq!(highlighter.lines.len(), 2);
+        assert_eq!(highlighter.tokens(1)[0].group, HighlightGroup::Keyword);
+        assert!(Highlighter::for_file("notes.txt").is_none());
+    }
+}
@@ -0,0 +1,176 @@
+use super::{is_ident, is_ident_start, HighlightGroup, LineState, Scanner};
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "type", "unsafe", "use",
+    "where", "while", "yield",
+];
+
+const PRIMITIVES: &[&str] = &[
+    "bool", "char", "f32", "f64", "i8", "i16", "i32", "i64", "i128", "isize", "str", "u8", "u16",
+    "u32", "u64", "u128", "usize",
+];
+
+// Closing delimiters for raw strings by the number of hashes
+const RAW_CLOSE: &[&str] = &["\"", "\"#", "\"##", "\"###"];
+
+pub fn tokenize(s: &mut Scanner, state: LineState) -> LineState {
+    let mut state = match state {
+        LineState::Comment(depth) => s.block_comment(0, depth, ("/*", "*/"), true),
+        LineState::String(close) => s.string(0, close, true),
+        LineState::RawString(close) => s.string(0, close, false),
+        state => state,
+    };
+
+    while let Some(c) = s.peek() {
+        let start = s.pos();
+        if s.eat("//") {
+            s.skip_to_end();
+            s.token(start, HighlightGroup::Comment);
+        } else if s.eat("/*") {
+            state = s.block_comment(start, 1, ("/*", "*/"), true);
+        } else if let Some((close, escapes)) = string_start(s) {
+            state = s.string(start, close, escapes);
+        } else if c == '\'' {
+            match char_literal_len(s.rest()) {
+                Some(len) => {
+                    s.advance(len);
+                    s.token(start, HighlightGroup::String);
+                }
+                // A lifetime
+                None => {
+                    s.bump();
+                    s.eat_while(is_ident);
+                    s.token(start, HighlightGroup::Special);
+                }
+            }
+        } else if c.is_ascii_digit() {
+            s.number();
+        } else if c == '#' && (s.rest()[1..].starts_with('[') || s.rest()[1..].starts_with("![")) {
+            attribute(s);
+        } else if is_ident_start(c) {
+            ident(s);
+        } else {
+            s.bump();
+        }
+    }
+    state
+}
+
+/**
+ * Eat the opening of a string literal, like ", b", r#" or br", returning
+ * how it closes and whether it has escapes
+ */
+fn string_start(s: &mut Scanner) -> Option<(&'static str, bool)> {
+    let rest = s.rest();
+    let unprefixed = rest.strip_prefix('b').unwrap_or(rest);
+    if let Some(raw) = unprefixed.strip_prefix('r') {
+        let hashes = raw.chars().take_while(|&c| c == '#').count();
+        if hashes < RAW_CLOSE.len() && raw[hashes..].starts_with('"') {
+            s.advance(rest.len() - raw.len() + hashes + 1);
+            return Some((RAW_CLOSE[hashes], false));
+        }
+    } else if unprefixed.starts_with('"') {
+        s.advance(rest.len() - unprefixed.len() + 1);
+        return Some(("\"", true));
+    }
+    None
+}
+
+/**
+ * The length of a char literal like 'a' or '\n', or None for a lifetime
+ */
+fn char_literal_len(rest: &str) -> Option<usize> {
+    let inner = &rest[1..];
+    if let Some(escaped) = inner.strip_prefix('\\') {
+        return escaped
+            .char_indices()
+            .skip(1)
+            .find(|&(_, c)| c == '\'')
+            .map(|(i, _)| i + 3);
+    }
+    let mut chars = inner.chars();
+    let c = chars.next()?;
+    match chars.next() {
+        Some('\'') => Some(2 + c.len_utf8()),
+        _ => None,
+    }
+}
+
+/**
+ * #[...] and #![...], up to the matching bracket
+ */
+fn attribute(s: &mut Scanner) {
+    let start = s.pos();
+    let mut depth = 0;
+    while let Some(c) = s.bump() {
+        match c {
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            _ => (),
+        }
+    }
+    s.token(start, HighlightGroup::PreProc);
+}
+
+fn ident(s: &mut Scanner) {
+    let start = s.pos();
+    let word = s.eat_while(is_ident);
+    let group = if s.peek() == Some('!') && s.peek_nth(1) != Some('=') {
+        s.bump();
+        HighlightGroup::PreProc
+    } else if KEYWORDS.contains(&word) {
+        HighlightGroup::Keyword
+    } else if word == "true" || word == "false" {
+        HighlightGroup::Boolean
+    } else if PRIMITIVES.contains(&word) || word.starts_with(char::is_uppercase) {
+        HighlightGroup::Type
+    } else if s.peek() == Some('(') || s.rest().starts_with("::<") {
+        HighlightGroup::Function
+    } else {
+        return;
+    };
+    s.token(start, group);
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::Grammar;
+    use super::*;
+
+    #[test]
+    fn test_rust() {
+        let grammar = Grammar::for_file("lib.rs").unwrap();
+        let line = "let s: &'a str = r#\"x\"#; println!(\"{}\", '\\n'); // done";
+        let (tokens, state) = grammar.tokenize(line, LineState::Normal);
+        let groups = tokens
+            .iter()
+            .map(|token| (&line[token.start..token.end], token.group))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            groups,
+            vec![
+                ("let", HighlightGroup::Keyword),
+                ("'a", HighlightGroup::Special),
+                ("str", HighlightGroup::Type),
+                ("r#\"x\"#", HighlightGroup::String),
+                ("println!", HighlightGroup::PreProc),
+                ("\"{}\"", HighlightGroup::String),
+                ("'\\n'", HighlightGroup::String),
+                ("// done", HighlightGroup::Comment),
+            ]
+        );
+        assert_eq!(state, LineState::Normal);
+
+        let (_, state) = grammar.tokenize("/* /* nested */", LineState::Normal);
+        assert_eq!(state, LineState::Comment(1));
+        let (_, state) = grammar.tokenize("let s = \"open", LineState::Normal);
+        assert_eq!(state, LineState::String("\""));
+    }
+}
@@ -0,0 +1,124 @@
+use super::{is_ident, HighlightGroup, LineState, Scanner};
+
+const KEYWORDS: &[&str] = &[
+    "break", "case", "continue", "declare", "do", "done", "elif", "else", "esac", "export", "fi",
+    "for", "function", "if", "in", "local", "readonly", "return", "select", "then", "unset",
+    "until", "while",
+];
+
+fn is_word(c: char) -> bool {
+    !c.is_whitespace() && !"'\"$;|&()<>`=#\\".contains(c)
+}
+
+pub fn tokenize(s: &mut Scanner, state: LineState) -> LineState {
+    let mut state = match state {
+        LineState::String(close) => s.string(0, close, true),
+        LineState::RawString(close) => s.string(0, close, false),
+        state => state,
+    };
+
+    // Keywords only count where a command could start
+    let mut command_start = true;
+    while let Some(c) = s.peek() {
+        let start = s.pos();
+        match c {
+            '#' if s.prev().is_none_or(char::is_whitespace) => {
+                s.skip_to_end();
+                s.token(start, HighlightGroup::Comment);
+            }
+            '\'' => {
+                s.bump();
+                state = s.string(start, "'", false);
+            }
+            '"' => {
+                s.bump();
+                state = s.string(start, "\"", true);
+            }
+            '$' => variable(s),
+            '\\' => {
+                s.bump();
+                s.bump();
+            }
+            ';' | '|' | '&' | '(' | '`' | '{' => {
+                s.bump();
+                command_start = true;
+            }
+            c if c.is_whitespace() => {
+                s.bump();
+            }
+            c if c.is_ascii_digit() => {
+                s.number();
+                command_start = false;
+            }
+            c if is_word(c) => {
+                let word = s.eat_while(is_word);
+                if command_start && s.peek() == Some('=') {
+                    s.token(start, HighlightGroup::Identifier);
+                    s.bump();
+                } else if command_start && KEYWORDS.contains(&word) {
+                    s.token(start, HighlightGroup::Keyword);
+                } else {
+                    command_start = false;
+                }
+            }
+            _ => {
+                s.bump();
+            }
+        }
+    }
+    state
+}
+
+/**
+ * $name, ${name...} and special parameters like $1 and $@
+ */
+fn variable(s: &mut Scanner) {
+    let start = s.pos();
+    s.bump();
+    if s.eat("{") {
+        s.eat_while(|c| c != '}');
+        s.eat("}");
+    } else if s.eat_while(is_ident).is_empty() {
+        match s.peek() {
+            Some(c) if "@*#?$!-".contains(c) => {
+                s.bump();
+            }
+            _ => return,
+        }
+    }
+    s.token(start, HighlightGroup::Identifier);
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::Grammar;
+    use super::*;
+
+    #[test]
+    fn test_shell() {
+        let grammar = Grammar::for_file("build.sh").unwrap();
+        let line = "if [ -n \"$1\" ]; then NAME=${1:-x}; echo 'if' $NAME; fi # done";
+        let (tokens, _) = grammar.tokenize(line, LineState::Normal);
+        let groups = tokens
+            .iter()
+            .map(|token| (&line[token.start..token.end], token.group))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            groups,
+            vec![
+                ("if", HighlightGroup::Keyword),
+                ("\"$1\"", HighlightGroup::String),
+                ("then", HighlightGroup::Keyword),
+                ("NAME", HighlightGroup::Identifier),
+                ("${1:-x}", HighlightGroup::Identifier),
+                ("'if'", HighlightGroup::String),
+                ("$NAME", HighlightGroup::Identifier),
+                ("fi", HighlightGroup::Keyword),
+                ("# done", HighlightGroup::Comment),
+            ]
+        );
+
+        let (_, state) = grammar.tokenize("echo 'open", LineState::Normal);
+        assert_eq!(state, LineState::RawString("'"));
+    }
+}
@@ -0,0 +1,91 @@
+use super::{HighlightGroup, LineState, Scanner};
+
+fn is_bare_key(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.'
+}
+
+pub fn tokenize(s: &mut Scanner, state: LineState) -> LineState {
+    let mut state = match state {
+        LineState::String(close) => s.string(0, close, true),
+        LineState::RawString(close) => s.string(0, close, false),
+        state => state,
+    };
+
+    // Keys start lines, and follow commas and braces in inline tables
+    let mut expect_key = state == LineState::Normal;
+    while let Some(c) = s.peek() {
+        let start = s.pos();
+        if c == '#' {
+            s.skip_to_end();
+            s.token(start, HighlightGroup::Comment);
+        } else if c == '[' && s.at_line_start() {
+            s.eat_while(|c| c != ']');
+            s.eat_while(|c| c == ']');
+            s.token(start, HighlightGroup::Title);
+        } else if s.eat("\"\"\"") {
+            state = s.string(start, "\"\"\"", true);
+        } else if s.eat("'''") {
+            state = s.string(start, "'''", false);
+        } else if s.eat("\"") {
+            // Other strings can't span lines
+            s.string(start, "\"", true);
+        } else if s.eat("'") {
+            s.string(start, "'", false);
+        } else if c.is_ascii_digit() || ((c == '+' || c == '-') && !expect_key) {
+            s.bump();
+            s.eat_while(|c| is_bare_key(c) || c == ':' || c == '+');
+            s.token(start, HighlightGroup::Number);
+        } else if is_bare_key(c) {
+            let word = s.eat_while(is_bare_key);
+            let group = match word {
+                _ if expect_key => HighlightGroup::Identifier,
+                "true" | "false" => HighlightGroup::Boolean,
+                "inf" | "nan" => HighlightGroup::Number,
+                _ => continue,
+            };
+            s.token(start, group);
+        } else {
+            match c {
+                '=' => expect_key = false,
+                ',' | '{' => expect_key = true,
+                _ => (),
+            }
+            s.bump();
+        }
+    }
+    state
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::Grammar;
+    use super::*;
+
+    #[test]
+    fn test_toml() {
+        let grammar = Grammar::for_file("Cargo.toml").unwrap();
+        let line = "dep = { version = \"1.0\", default-features = false } # pinned";
+        let (tokens, _) = grammar.tokenize(line, LineState::Normal);
+        let groups = tokens
+            .iter()
+            .map(|token| (&line[token.start..token.end], token.group))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            groups,
+            vec![
+                ("dep", HighlightGroup::Identifier),
+                ("version", HighlightGroup::Identifier),
+                ("\"1.0\"", HighlightGroup::String),
+                ("default-features", HighlightGroup::Identifier),
+                ("false", HighlightGroup::Boolean),
+                ("# pinned", HighlightGroup::Comment),
+            ]
+        );
+
+        let (tokens, state) = grammar.tokenize("[[bin]]", LineState::Normal);
+        assert_eq!(tokens[0].group, HighlightGroup::Title);
+        assert_eq!(state, LineState::Normal);
+        let (_, state) = grammar.tokenize("text = \"\"\"", LineState::Normal);
+        assert_eq!(state, LineState::String("\"\"\""));
+    }
+}
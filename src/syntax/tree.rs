@@ -0,0 +1,346 @@
+use super::{HighlightGroup, Token};
+use crate::buffer::Position;
+use crate::text::{Edit, PieceTableBuffer, TextBuffer};
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Range;
+use std::rc::Rc;
+use tree_sitter::{InputEdit, Language, Node, Parser, Point, Query, QueryCursor, Tree};
+
+/**
+ * A statically linked tree-sitter grammar, for the regex grammar of the
+ * same name
+ */
+struct TreeLanguage {
+    name: &'static str,
+    language: fn() -> Language,
+    highlights: &'static str,
+    // The node kinds af selects and ]f jumps to
+    functions: &'static [&'static str],
+}
+
+static LANGUAGES: &[TreeLanguage] = &[
+    TreeLanguage {
+        name: "rust",
+        language: tree_sitter_rust::language,
+        highlights: tree_sitter_rust::HIGHLIGHT_QUERY,
+        functions: &["function_item"],
+    },
+    TreeLanguage {
+        name: "toml",
+        language: tree_sitter_toml::language,
+        highlights: tree_sitter_toml::HIGHLIGHT_QUERY,
+        functions: &["table", "table_array_element"],
+    },
+    TreeLanguage {
+        name: "markdown",
+        language: tree_sitter_md::language,
+        highlights: tree_sitter_md::HIGHLIGHT_QUERY_BLOCK,
+        functions: &["section"],
+    },
+    TreeLanguage {
+        name: "sh",
+        language: tree_sitter_bash::language,
+        highlights: tree_sitter_bash::HIGHLIGHT_QUERY,
+        functions: &["function_definition"],
+    },
+];
+
+/**
+ * Capture names from the highlight queries, most specific first
+ */
+const CAPTURES: &[(&str, HighlightGroup)] = &[
+    ("comment", HighlightGroup::Comment),
+    ("string.escape", HighlightGroup::Special),
+    ("escape", HighlightGroup::Special),
+    ("string", HighlightGroup::String),
+    ("number", HighlightGroup::Number),
+    ("boolean", HighlightGroup::Boolean),
+    ("constant.builtin", HighlightGroup::Boolean),
+    ("constant", HighlightGroup::Constant),
+    ("keyword", HighlightGroup::Keyword),
+    ("type", HighlightGroup::Type),
+    ("constructor", HighlightGroup::Type),
+    ("function.macro", HighlightGroup::PreProc),
+    ("function", HighlightGroup::Function),
+    ("attribute", HighlightGroup::PreProc),
+    ("label", HighlightGroup::Special),
+    ("property", HighlightGroup::Identifier),
+    ("variable.parameter", HighlightGroup::Identifier),
+    ("punctuation.special", HighlightGroup::Special),
+    ("text.title", HighlightGroup::Title),
+    ("text.literal", HighlightGroup::String),
+    ("text.uri", HighlightGroup::Underlined),
+    ("text.reference", HighlightGroup::Underlined),
+    ("text.strong", HighlightGroup::Bold),
+    ("text.emphasis", HighlightGroup::Italic),
+];
+
+fn capture_group(name: &str) -> Option<HighlightGroup> {
+    CAPTURES
+        .iter()
+        .find(|(prefix, _)| {
+            name.strip_prefix(prefix)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+        })
+        .map(|(_, group)| *group)
+}
+
+/**
+ * A parse tree kept in step with a buffer. The buffer records its edits,
+ * which are applied to the old tree so the next parse can reuse it.
+ */
+pub struct SyntaxTree {
+    language: &'static TreeLanguage,
+    parser: Parser,
+    query: Rc<Query>,
+    tree: Option<Tree>,
+
+    // The text the tree was parsed from, and where each line starts in it
+    text: String,
+    line_starts: Vec<usize>,
+}
+
+impl SyntaxTree {
+    pub fn new(name: &str) -> Option<Self> {
+        let language = LANGUAGES.iter().find(|language| language.name == name)?;
+        let query = Query::new((language.language)(), language.highlights).ok()?;
+        SyntaxTree::with_query(language, Rc::new(query))
+    }
+
+    fn with_query(language: &'static TreeLanguage, query: Rc<Query>) -> Option<Self> {
+        let mut parser = Parser::new();
+        parser.set_language((language.language)()).ok()?;
+        Some(SyntaxTree {
+            language,
+            parser,
+            query,
+            tree: None,
+            text: String::new(),
+            line_starts: vec![0],
+        })
+    }
+
+    /**
+     * Parse the buffer again if it changed, reusing the parts of the tree
+     * its edits didn't touch. Returns the first line whose highlighting
+     * may have changed.
+     */
+    pub fn sync(&mut self, buffer: &mut PieceTableBuffer) -> Option<usize> {
+        // Starts recording edits the first time through
+        let edits = buffer.edits.replace(vec![]).unwrap_or_default();
+        if self.tree.is_some() && edits.is_empty() {
+            return None;
+        }
+
+        let mut old_tree = self.tree.take();
+        if let Some(tree) = &mut old_tree {
+            for edit in edits.iter() {
+                tree.edit(&input_edit(edit));
+            }
+        }
+        self.text = buffer.to_string();
+        self.line_starts = std::iter::once(0)
+            .chain(self.text.match_indices('\n').map(|(idx, _)| idx + 1))
+            .collect();
+
+        let tree = self.parser.parse(&self.text, old_tree.as_ref())?;
+        let changed = match &old_tree {
+            Some(old_tree) => old_tree
+                .changed_ranges(&tree)
+                .map(|range| range.start_point.row)
+                .chain(edits.iter().map(|edit| edit.start.line))
+                .min(),
+            None => Some(0),
+        };
+        self.tree = Some(tree);
+        changed
+    }
+
+    /**
+     * Tokens for each of the lines, from the highlight query
+     */
+    pub fn highlight(&self, lines: Range<usize>) -> Vec<Vec<Token>> {
+        let mut tokens = vec![vec![]; lines.len()];
+        let tree = match &self.tree {
+            Some(tree) if !lines.is_empty() => tree,
+            _ => return tokens,
+        };
+
+        let mut cursor = QueryCursor::new();
+        cursor.set_byte_range(self.line_start(lines.start)..self.line_start(lines.end));
+        let names = self.query.capture_names();
+        let mut seen = HashSet::new();
+        let captures = cursor.captures(&self.query, tree.root_node(), self.text.as_bytes());
+        for (query_match, idx) in captures {
+            let capture = query_match.captures[idx];
+            let node = capture.node;
+
+            // The first pattern to capture a node wins
+            if !seen.insert(node.id()) {
+                continue;
+            }
+            let group = match capture_group(names[capture.index as usize].as_str()) {
+                Some(group) => group,
+                None => continue,
+            };
+
+            let first = node.start_position().row.max(lines.start);
+            let last = node.end_position().row.min(lines.end - 1);
+            for row in first..=last {
+                let line_start = self.line_start(row);
+                let start = node.start_byte().max(line_start) - line_start;
+                let end = node.end_byte().min(self.line_start(row + 1)) - line_start;
+                if end > start {
+                    tokens[row - lines.start].push(Token { start, end, group });
+                }
+            }
+        }
+        tokens
+    }
+
+    /**
+     * The smallest named node that's larger than the inclusive range
+     */
+    pub fn expand(&self, start: Position, end: Position) -> Option<(Position, Position)> {
+        let start = self.byte(start);
+        let end = self.byte(end);
+        let end = end + self.text[end..].chars().next().map_or(0, char::len_utf8);
+
+        let root = self.tree.as_ref()?.root_node();
+        let mut node = root.named_descendant_for_byte_range(start, end)?;
+        while node.start_byte() >= start && node.end_byte() <= end {
+            node = node.parent()?;
+        }
+        Some(self.node_range(node))
+    }
+
+    pub fn function_around(&self, pos: Position) -> Option<(Position, Position)> {
+        let byte = self.byte(pos);
+        let root = self.tree.as_ref()?.root_node();
+        let mut node = root.descendant_for_byte_range(byte, byte)?;
+        while !self.language.functions.contains(&node.kind()) {
+            node = node.parent()?;
+        }
+        Some(self.node_range(node))
+    }
+
+    /**
+     * The start of the next (or previous) function
+     */
+    pub fn next_function(&self, pos: Position, forward: bool) -> Option<Position> {
+        let byte = self.byte(pos);
+        let mut starts = vec![];
+        let mut cursor = self.tree.as_ref()?.walk();
+        'walk: loop {
+            if self.language.functions.contains(&cursor.node().kind()) {
+                starts.push(cursor.node().start_byte());
+            }
+            if cursor.goto_first_child() {
+                continue;
+            }
+            while !cursor.goto_next_sibling() {
+                if !cursor.goto_parent() {
+                    break 'walk;
+                }
+            }
+        }
+
+        let start = if forward {
+            starts.into_iter().find(|&start| start > byte)
+        } else {
+            starts.into_iter().rfind(|&start| start < byte)
+        };
+        start.map(|start| self.position(start))
+    }
+
+    fn line_start(&self, line: usize) -> usize {
+        self.line_starts
+            .get(line)
+            .copied()
+            .unwrap_or(self.text.len())
+    }
+
+    fn byte(&self, pos: Position) -> usize {
+        (self.line_start(pos.line) + pos.col).min(self.text.len())
+    }
+
+    fn position(&self, byte: usize) -> Position {
+        let line = match self.line_starts.binary_search(&byte) {
+            Ok(line) => line,
+            Err(line) => line - 1,
+        };
+        Position::new(line, byte - self.line_starts[line])
+    }
+
+    /**
+     * A node's first and last characters
+     */
+    fn node_range(&self, node: Node) -> (Position, Position) {
+        let end = node.end_byte();
+        let last = self.text[..end]
+            .chars()
+            .next_back()
+            .map_or(0, char::len_utf8);
+        let start = node.start_byte();
+        (self.position(start), self.position((end - last).max(start)))
+    }
+}
+
+impl Clone for SyntaxTree {
+    fn clone(&self) -> Self {
+        let mut tree = SyntaxTree::with_query(self.language, self.query.clone())
+            .expect("language was loaded before");
+        tree.tree = self.tree.clone();
+        tree.text = self.text.clone();
+        tree.line_starts = self.line_starts.clone();
+        tree
+    }
+}
+
+impl fmt::Debug for SyntaxTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SyntaxTree({})", self.language.name)
+    }
+}
+
+fn point(pos: Position) -> Point {
+    Point::new(pos.line, pos.col)
+}
+
+fn input_edit(edit: &Edit) -> InputEdit {
+    InputEdit {
+        start_byte: edit.start_byte,
+        old_end_byte: edit.old_end_byte,
+        new_end_byte: edit.new_end_byte,
+        start_position: point(edit.start),
+        old_end_position: point(edit.old_end),
+        new_end_position: point(edit.new_end),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_syntax_tree() {
+        let mut buffer = PieceTableBuffer::new("fn a() {}\nfn b() { 1 }\n".to_string());
+        let mut tree = SyntaxTree::new("rust").unwrap();
+        assert_eq!(tree.sync(&mut buffer), Some(0));
+        assert_eq!(tree.sync(&mut buffer), None);
+
+        let b = (Position::new(1, 0), Position::new(1, 11));
+        assert_eq!(tree.function_around(Position::new(1, 9)), Some(b));
+        assert_eq!(tree.next_function(Position::new(0, 3), true), Some(b.0));
+        let one = Position::new(1, 9);
+        assert_ne!(tree.expand(one, one), None);
+
+        // Edits reach the tree incrementally
+        buffer.insert_str(Position::new(2, 0), "fn c() {}\n");
+        assert!(tree.sync(&mut buffer).is_some());
+        assert_eq!(tree.next_function(b.0, true), Some(Position::new(2, 0)));
+        let tokens = tree.highlight(0..3);
+        assert_eq!(tokens[2][0].group, HighlightGroup::Keyword);
+    }
+}
@@ -0,0 +1,170 @@
+use crate::editor::Editor;
+use crate::ex::ExError;
+use crate::window::{Window, Windows};
+use std::mem;
+
+/**
+ * A tab page other than the current one: its windows, and where the
+ * window that was current in it left off
+ */
+pub struct TabPage {
+    pub windows: Windows,
+    pub window: Window,
+}
+
+/**
+ * Every tab page. The current one lives in the editor's windows, and
+ * `current` is its position among the others.
+ */
+pub struct Tabs {
+    pub current: usize,
+    hidden: Vec<TabPage>,
+}
+
+impl Tabs {
+    pub fn new() -> Self {
+        Tabs {
+            current: 0,
+            hidden: vec![],
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.hidden.len() + 1
+    }
+}
+
+impl Default for Tabs {
+    fn default() -> Self {
+        Tabs::new()
+    }
+}
+
+impl Editor {
+    /**
+     * Open a tab page after the current one, with a single window showing
+     * the current buffer with the global window options
+     */
+    pub fn new_tab(&mut self) {
+        let page = self.save_tab();
+        self.tabs.hidden.insert(self.tabs.current, page);
+        self.tabs.current += 1;
+        self.text_window_state.options = self.options.window.clone();
+    }
+
+    /**
+     * Switch to the tab page at a 0-based position
+     */
+    pub fn switch_tab(&mut self, idx: usize) -> Result<(), ExError> {
+        if idx >= self.tabs.count() {
+            return Err(ExError::NoSuchTab(idx + 1));
+        }
+        if idx == self.tabs.current {
+            return Ok(());
+        }
+
+        // With the current page back in place the list has every tab in order
+        let page = self.save_tab();
+        self.tabs.hidden.insert(self.tabs.current, page);
+        let page = self.tabs.hidden.remove(idx);
+        self.tabs.current = idx;
+        self.restore_tab(page);
+        Ok(())
+    }
+
+    /**
+     * Switch to the tab page `count` places after (or before) the current
+     * one, wrapping around
+     */
+    pub fn cycle_tab(&mut self, count: isize) -> Result<(), ExError> {
+        let idx = (self.tabs.current as isize + count).rem_euclid(self.tabs.count() as isize);
+        self.switch_tab(idx as usize)
+    }
+
+    /**
+     * Close the current tab page and its windows, moving to the next one.
+     * The buffers it showed stay loaded.
+     */
+    pub fn close_tab(&mut self) -> Result<(), ExError> {
+        if self.tabs.hidden.is_empty() {
+            return Err(ExError::LastTab);
+        }
+        let idx = self.tabs.current.min(self.tabs.hidden.len() - 1);
+        let page = self.tabs.hidden.remove(idx);
+        self.tabs.current = idx;
+        self.restore_tab(page);
+        Ok(())
+    }
+
+    /**
+     * The name of the buffer in each tab page's current window, in order
+     */
+    pub fn tab_labels(&self) -> Vec<String> {
+        let name = |filename: Option<&String>| {
+            filename.map_or("[No Name]".to_string(), |name| name.to_string())
+        };
+        let mut labels = self
+            .tabs
+            .hidden
+            .iter()
+            .map(|page| match self.buffers.get(page.window.buffer) {
+                Some(entry) => name(entry.filename.as_ref()),
+                None => name(self.filename.as_ref()),
+            })
+            .collect::<Vec<String>>();
+        labels.insert(self.tabs.current, name(self.filename.as_ref()));
+        labels
+    }
+
+    /**
+     * Take the current tab page, leaving a single window in its place
+     */
+    fn save_tab(&mut self) -> TabPage {
+        let window = self.current_window(self.windows.current);
+        TabPage {
+            windows: mem::replace(&mut self.windows, Windows::new()),
+            window,
+        }
+    }
+
+    fn restore_tab(&mut self, page: TabPage) {
+        self.windows = page.windows;
+        self.restore_window(page.window);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::buffer::Position;
+    use crate::ex;
+
+    #[test]
+    fn test_tabs() {
+        let mut editor = Editor::new();
+        editor.text_buffer.insert('a');
+        editor.filename = Some("one".to_string());
+
+        ex::run("tabnew", &mut editor).unwrap();
+        ex::run("vsplit", &mut editor).unwrap();
+        assert_eq!(editor.tabs.count(), 2);
+        assert_eq!(editor.windows.count(), 2);
+        assert_eq!(editor.tab_labels(), vec!["one", "[No Name]"]);
+
+        // Each tab keeps its own layout and cursor
+        ex::run("tabn 1", &mut editor).unwrap();
+        assert_eq!(editor.windows.count(), 1);
+        assert_eq!(editor.filename.as_deref(), Some("one"));
+        assert_eq!(editor.text_buffer.cursor(), Position::new(0, 1));
+        ex::run("tabn", &mut editor).unwrap();
+        assert_eq!(editor.windows.count(), 2);
+        assert!(ex::run("tabn 3", &mut editor).is_err());
+
+        // Closing the last window of a tab closes the tab
+        ex::run("q", &mut editor).unwrap();
+        ex::run("q", &mut editor).unwrap();
+        assert_eq!(editor.tabs.count(), 1);
+        assert_eq!(editor.filename.as_deref(), Some("one"));
+        assert!(ex::run("tabclose", &mut editor).is_err());
+    }
+}
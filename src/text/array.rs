@@ -13,19 +13,8 @@ pub struct ForwardIterator<'a> {
 
 impl<'a> ForwardIterator<'a> {
     pub fn new(array_buffer: &'a ArrayBuffer, pos: Position) -> Self {
-        let mut remaining_lines = pos.line;
-        let mut remaining_cols = pos.col;
-        let chars = array_buffer.text.chars().skip_while(move |&c| {
-            if c == '\n' && remaining_lines > 0 {
-                remaining_lines -= 1;
-                false
-            } else if remaining_cols > 0 {
-                remaining_cols -= 1;
-                false
-            } else {
-                true
-            }
-        });
+        let idx = array_buffer.pos_idx(pos);
+        let chars = array_buffer.text[idx..].chars();
 
         ForwardIterator {
             chars: Box::new(chars),
@@ -108,3 +97,17 @@ impl TextBuffer for ArrayBuffer {
         ForwardIterator::new(self, pos)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_chars() {
+        let buffer = ArrayBuffer::new("one\ntwo\n".to_string());
+        let rest = buffer.chars(Position::new(1, 1)).collect::<String>();
+        assert_eq!(rest, "wo\n");
+        assert_eq!(buffer.line(0), "one");
+        assert_eq!(buffer.line_length(1), 3);
+    }
+}
@@ -2,7 +2,7 @@ mod array;
 mod piece_table;
 
 pub use array::ArrayBuffer;
-pub use piece_table::PieceTableBuffer;
+pub use piece_table::{Anchor, Edit, PieceTableBuffer, UndoGroup};
 
 use crate::buffer::Position;
 use tui::text::Text;
@@ -39,6 +39,18 @@ pub trait TextBuffer {
 
     fn delete<T: Into<Range>>(&mut self, range: T);
 
+    fn insert_str(&mut self, pos: Position, text: &str) {
+        let mut pos = pos;
+        for c in text.chars() {
+            self.insert(pos, c);
+            if c == '\n' {
+                pos = Position::new(pos.line + 1, 0);
+            } else {
+                pos.col += c.len_utf8();
+            }
+        }
+    }
+
     fn chars<'a>(&'a self, pos: Position) -> Self::Iter<'a>;
 
     fn line_count(&self) -> usize {
@@ -0,0 +1,62 @@
+use super::PieceTableBuffer;
+use std::rc::Rc;
+
+/**
+ * A reference to a character in original or added. Pieces only ever point
+ * at text, so the character keeps its identity through edits elsewhere
+ * and can be found again until it's deleted.
+ */
+#[derive(Debug, Clone)]
+pub struct Anchor {
+    source: Rc<String>,
+    idx: usize,
+}
+
+impl PieceTableBuffer {
+    /**
+     * Anchor the first character of a line. An empty line is anchored by
+     * its newline.
+     */
+    pub fn line_anchor(&self, line: usize) -> Option<Anchor> {
+        let location = self.line_start(line);
+        let mut offset = location.offset;
+        for piece in self.pieces.get(location.idx..)? {
+            if offset < piece.length {
+                return Some(Anchor {
+                    source: piece.source.clone(),
+                    idx: piece.start + offset,
+                });
+            }
+            offset = 0;
+        }
+        None
+    }
+
+    /**
+     * The line the anchor starts. None if the character was deleted or is
+     * no longer at the start of a line, e.g. after a join.
+     */
+    pub fn anchor_line(&self, anchor: &Anchor) -> Option<usize> {
+        let mut line = 0;
+        let mut at_line_start = true;
+        for piece in self.pieces.iter() {
+            let contains = Rc::ptr_eq(&piece.source, &anchor.source)
+                && anchor.idx >= piece.start
+                && anchor.idx < piece.start + piece.length;
+            if contains {
+                let before = &piece.text()[..anchor.idx - piece.start];
+                let starts_line = match before.chars().last() {
+                    Some(c) => c == '\n',
+                    None => at_line_start,
+                };
+                return starts_line.then(|| line + before.matches('\n').count());
+            }
+
+            line += piece.newline_count;
+            if let Some(c) = piece.text().chars().last() {
+                at_line_start = c == '\n';
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,132 @@
+use super::PieceTableBuffer;
+use crate::buffer::Position;
+use crate::text::TextBuffer;
+
+/**
+ * A change to the text as byte offsets and positions, with columns in
+ * bytes. This is what a syntax tree needs to be edited in step with the
+ * text instead of parsed from scratch.
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Edit {
+    pub start_byte: usize,
+    pub old_end_byte: usize,
+    pub new_end_byte: usize,
+    pub start: Position,
+    pub old_end: Position,
+    pub new_end: Position,
+}
+
+impl PieceTableBuffer {
+    pub fn byte_offset(&self, pos: Position) -> usize {
+        let location = self.location(pos);
+        let before = self
+            .pieces
+            .iter()
+            .take(location.idx)
+            .map(|piece| piece.length)
+            .sum::<usize>();
+        before + location.offset
+    }
+
+    /**
+     * The position of a byte offset, clamped to the end of the text
+     */
+    pub fn position_of(&self, byte: usize) -> Position {
+        let mut pos = Position::new(0, 0);
+        let mut remaining = byte;
+        for piece in self.pieces.iter() {
+            if remaining == 0 {
+                break;
+            }
+            let text = &piece.text()[..remaining.min(piece.length)];
+            match text.rfind('\n') {
+                Some(idx) => {
+                    pos.line += text.matches('\n').count();
+                    pos.col = text.len() - idx - 1;
+                }
+                None => pos.col += text.len(),
+            }
+            remaining -= text.len();
+        }
+        pos
+    }
+
+    pub(super) fn record_insert(&mut self, pos: Position, text: &str) {
+        if self.edits.is_none() {
+            return;
+        }
+        let start = Position::new(pos.line, pos.col.min(self.line_length(pos.line)));
+        let start_byte = self.byte_offset(start);
+        let new_end = match text.rfind('\n') {
+            Some(idx) => Position::new(
+                start.line + text.matches('\n').count(),
+                text.len() - idx - 1,
+            ),
+            None => Position::new(start.line, start.col + text.len()),
+        };
+        self.record(Edit {
+            start_byte,
+            old_end_byte: start_byte,
+            new_end_byte: start_byte + text.len(),
+            start,
+            old_end: start,
+            new_end,
+        });
+    }
+
+    pub(super) fn record_delete(&mut self, pos: Position, length: usize) {
+        if self.edits.is_none() {
+            return;
+        }
+        let start_byte = self.byte_offset(pos);
+        let start = self.position_of(start_byte);
+
+        let mut old_end = start;
+        let mut deleted = 0;
+        for c in self.chars(start) {
+            if deleted >= length {
+                break;
+            }
+            deleted += c.len_utf8();
+            if c == '\n' {
+                old_end = Position::new(old_end.line + 1, 0);
+            } else {
+                old_end.col += c.len_utf8();
+            }
+        }
+        self.record(Edit {
+            start_byte,
+            old_end_byte: start_byte + deleted,
+            new_end_byte: start_byte,
+            start,
+            old_end,
+            new_end: start,
+        });
+    }
+
+    /**
+     * The edit for swapping pieces start..end for new ones, as undo does.
+     * The new end position can only be found after the swap.
+     */
+    pub(super) fn splice_edit(&self, start: usize, end: usize, new_length: usize) -> Option<Edit> {
+        self.edits.as_ref()?;
+        let lengths = self.pieces.iter().map(|piece| piece.length);
+        let start_byte = lengths.clone().take(start).sum::<usize>();
+        let old_end_byte = start_byte + lengths.skip(start).take(end - start).sum::<usize>();
+        Some(Edit {
+            start_byte,
+            old_end_byte,
+            new_end_byte: start_byte + new_length,
+            start: self.position_of(start_byte),
+            old_end: self.position_of(old_end_byte),
+            new_end: Position::new(0, 0),
+        })
+    }
+
+    pub(super) fn record(&mut self, edit: Edit) {
+        if let Some(edits) = &mut self.edits {
+            edits.push(edit);
+        }
+    }
+}
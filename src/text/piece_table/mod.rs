@@ -2,14 +2,19 @@
 #[path = "./piece_table_test.rs"]
 mod test;
 
+mod anchor;
+mod edit;
 mod iterator;
 mod undo;
 
 use super::{Range, TextBuffer};
 use crate::buffer::Position;
+pub use anchor::Anchor;
+pub use edit::Edit;
 use iterator::ForwardIterator;
 use std::rc::Rc;
 use tui::text::{Span, Spans, Text};
+pub use undo::UndoGroup;
 use undo::UndoStep;
 
 /**
@@ -72,6 +77,15 @@ pub struct PieceTableBuffer {
 
     pub undo_steps: Vec<UndoStep>,
     pub cache_idx: Option<usize>,
+
+    // Bumped on every change, so others can tell if the text changed
+    pub change_tick: usize,
+
+    // The first line changed since the highlighter last caught up
+    pub changed_line: Option<usize>,
+
+    // Edits since a syntax tree last caught up, while one is listening
+    pub edits: Option<Vec<Edit>>,
 }
 
 impl PieceTableBuffer {
@@ -83,6 +97,9 @@ impl PieceTableBuffer {
             pieces: vec![Piece::new(original.clone(), 0, original.len())],
             undo_steps: Vec::new(),
             cache_idx: None,
+            change_tick: 0,
+            changed_line: None,
+            edits: None,
         }
     }
 
@@ -125,6 +142,10 @@ impl PieceTableBuffer {
         location
     }
 
+    pub fn mark_changed(&mut self, line: usize) {
+        self.changed_line = Some(self.changed_line.map_or(line, |changed| changed.min(line)));
+    }
+
     // Find the location where a given line number starts
     fn line_start(&self, line: usize) -> Location {
         let mut location = Location { idx: 0, offset: 0 };
@@ -212,6 +233,9 @@ impl TextBuffer for PieceTableBuffer {
 
     fn insert(&mut self, pos: Position, c: char) {
         let location = self.location(pos);
+        self.change_tick += 1;
+        self.mark_changed(pos.line);
+        self.record_insert(pos, c.encode_utf8(&mut [0; 4]));
 
         // Because the added vector is append only, all slice refs
         // will remain valid.
@@ -269,8 +293,50 @@ impl TextBuffer for PieceTableBuffer {
             .push(UndoStep::new(location.idx, end, old_pieces))
     }
 
+    /**
+     * Insert the text as a single piece, and a single undo step
+     */
+    fn insert_str(&mut self, pos: Position, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        let location = self.location(pos);
+        self.change_tick += 1;
+        self.mark_changed(pos.line);
+        self.record_insert(pos, text);
+
+        let start = self.added.len();
+        unsafe {
+            Rc::get_mut_unchecked(&mut self.added).push_str(text);
+        }
+        let new_piece = Piece::new(self.added.clone(), start, text.len());
+
+        // Split the piece the text goes into, unless it goes at the end
+        let (replaced, new_pieces) = match self.pieces.get(location.idx) {
+            Some(piece) => {
+                let (left, right) = piece.split(location.offset);
+                let new_pieces: Vec<Piece> = [left, new_piece, right]
+                    .iter()
+                    .filter(|piece| piece.length != 0)
+                    .cloned()
+                    .collect();
+                (location.idx..location.idx + 1, new_pieces)
+            }
+            None => (location.idx..location.idx, vec![new_piece]),
+        };
+        let end = location.idx + new_pieces.len();
+        let old_pieces: Vec<Piece> = self.pieces.splice(replaced, new_pieces).collect();
+
+        self.undo_steps
+            .push(UndoStep::new(location.idx, end, old_pieces));
+        self.cache_idx = None;
+    }
+
     fn delete<T: Into<Range>>(&mut self, range: T) {
         let range = range.into();
+        self.change_tick += 1;
+        self.mark_changed(range.start.line);
+        self.record_delete(range.start, range.length);
         let location = self.location(range.start);
 
         let mut offset = location.offset;
@@ -111,3 +111,105 @@ fn test_line_length() {
     // assert_eq!(table.line_length(2), 17);
     assert_eq!(table.line_length(3), 0);
 }
+
+#[test]
+fn test_undo_group() {
+    let mut table = PieceTableBuffer::new("the quick\nbrown fox\n".to_string());
+    insert_seq(0, 0, "so ", &mut table);
+
+    let group = table.start_undo_group();
+    table.delete(Range::new(pos(1, 0), 6));
+    insert_seq(1, 0, "red ", &mut table);
+    table.insert_str(pos(0, 3), "very ");
+    table.end_undo_group(group);
+    assert_eq!(
+        table.to_string(),
+        "so very the quick\nred fox\n".to_string()
+    );
+
+    table.undo();
+    assert_eq!(table.to_string(), "so the quick\nbrown fox\n".to_string());
+}
+
+#[test]
+fn test_line_anchor() {
+    let mut table = PieceTableBuffer::new("one\n\nthree\nfour\n".to_string());
+    let empty = table.line_anchor(1).unwrap();
+    let three = table.line_anchor(2).unwrap();
+    let four = table.line_anchor(3).unwrap();
+    assert!(table.line_anchor(4).is_none());
+
+    // Lines inserted and deleted above move the anchors
+    insert_seq(0, 0, "zero\n", &mut table);
+    assert_eq!(table.anchor_line(&three), Some(3));
+    table.delete(Range::new(pos(1, 0), 4));
+    assert_eq!(table.anchor_line(&empty), Some(1));
+    assert_eq!(table.anchor_line(&three), Some(2));
+
+    // Deleted and joined lines are lost
+    table.delete(Range::new(pos(1, 0), 1));
+    assert_eq!(table.anchor_line(&empty), None);
+    table.delete(Range::new(pos(1, 5), 1));
+    assert_eq!(table.anchor_line(&four), None);
+    assert_eq!(table.to_string(), "zero\nthreefour\n".to_string());
+}
+
+#[test]
+fn test_edits() {
+    let mut table = PieceTableBuffer::new("ab\ncd".to_string());
+    table.insert(pos(1, 0), 'x');
+    assert!(table.edits.is_none());
+
+    table.edits = Some(vec![]);
+    table.insert(pos(1, 0), 'y');
+    table.delete(Range::new(pos(0, 1), 3));
+    table.undo();
+    assert_eq!(table.to_string(), "ab\nyxcd");
+
+    let edits = table.edits.take().unwrap();
+    assert_eq!(
+        (edits[0].start_byte, edits[0].new_end_byte, edits[0].new_end),
+        (3, 4, pos(1, 1))
+    );
+    assert_eq!(
+        (edits[1].old_end_byte, edits[1].start, edits[1].old_end),
+        (4, pos(0, 1), pos(1, 1))
+    );
+
+    // Undo covers whole pieces, so only the size of the change is exact
+    let undo = edits[2];
+    assert!(undo.start_byte <= 1 && undo.new_end_byte >= 4);
+    assert_eq!(undo.new_end_byte - undo.old_end_byte, 3);
+    assert_eq!(undo.new_end, table.position_of(undo.new_end_byte));
+
+    // Delete lengths are bytes
+    let mut table = PieceTableBuffer::new("héllo\n".to_string());
+    table.edits = Some(vec![]);
+    table.delete(Range::new(pos(0, 0), 3));
+    let edits = table.edits.take().unwrap();
+    assert_eq!((edits[0].old_end_byte, edits[0].old_end), (3, pos(0, 3)));
+}
+
+#[test]
+fn test_insert_str() {
+    let mut table = PieceTableBuffer::new("ab\ncd\n".to_string());
+    table.edits = Some(vec![]);
+    table.insert_str(pos(0, 1), "x\nyé");
+    assert_eq!(table.to_string(), "ax\nyéb\ncd\n".to_string());
+    assert_eq!(table.pieces.len(), 3);
+    assert_eq!(table.changed_line, Some(0));
+
+    let edits = table.edits.take().unwrap();
+    assert_eq!(edits.len(), 1);
+    assert_eq!(
+        (edits[0].start_byte, edits[0].new_end_byte, edits[0].new_end),
+        (1, 6, pos(1, 3))
+    );
+
+    table.insert_str(pos(3, 0), "end");
+    assert_eq!(table.to_string(), "ax\nyéb\ncd\nend".to_string());
+
+    table.undo();
+    table.undo();
+    assert_eq!(table.to_string(), "ab\ncd\n".to_string());
+}
@@ -19,6 +19,52 @@ impl PieceTableBuffer {
             Some(s) => s,
             None => return,
         };
+        let line = self
+            .pieces
+            .iter()
+            .take(step.start)
+            .map(|piece| piece.newline_count)
+            .sum();
+        self.mark_changed(line);
+
+        let new_length = step.pieces.iter().map(|piece| piece.length).sum();
+        let edit = self.splice_edit(step.start, step.end, new_length);
         self.pieces.splice(step.start..step.end, step.pieces);
+        self.change_tick += 1;
+        if let Some(mut edit) = edit {
+            edit.new_end = self.position_of(edit.new_end_byte);
+            self.record(edit);
+        }
+    }
+}
+
+/**
+ * A snapshot of the pieces taken before a compound edit. Ending the group
+ * replaces every undo step recorded since with a single step.
+ */
+pub struct UndoGroup {
+    mark: usize,
+    pieces: Vec<Piece>,
+}
+
+impl PieceTableBuffer {
+    pub fn start_undo_group(&mut self) -> UndoGroup {
+        // Don't let deletes in the group merge into an earlier step
+        self.cache_idx = None;
+        UndoGroup {
+            mark: self.undo_steps.len(),
+            pieces: self.pieces.clone(),
+        }
+    }
+
+    pub fn end_undo_group(&mut self, group: UndoGroup) {
+        self.cache_idx = None;
+        if self.undo_steps.len() <= group.mark {
+            return;
+        }
+
+        self.undo_steps.truncate(group.mark);
+        self.undo_steps
+            .push(UndoStep::new(0, self.pieces.len(), group.pieces));
     }
 }
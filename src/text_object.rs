@@ -31,7 +31,22 @@ impl TextObject {
         use TextObject::*;
 
         match self {
-            Charwise(_) => Range::new(Position::new(0, 0), 0),
+            Charwise(obj) => {
+                let mut pos = obj.start;
+                let mut length = 0;
+                for c in text.chars(obj.start) {
+                    length += c.len_utf8();
+                    if (pos.line, pos.col) >= (obj.end.line, obj.end.col) {
+                        break;
+                    }
+                    pos = if c == '\n' {
+                        Position::new(pos.line + 1, 0)
+                    } else {
+                        Position::new(pos.line, pos.col + c.len_utf8())
+                    };
+                }
+                Range::new(obj.start, length)
+            }
             Linewise(obj) => {
                 // TODO optimize this
                 let length = (obj.start..=obj.end)
@@ -1,51 +1,259 @@
+use crate::buffer::Position;
+use crate::cmdline_window;
 use crate::editor::Editor;
-use crate::state::State;
-use crate::text::TextBuffer;
+use crate::ex::completion::Completion;
+use crate::file::FileInfo;
+use crate::message::MessageKind;
+use crate::state::{CommandState, SearchState, State};
+use crate::text::{Range, TextBuffer};
 use crate::ui::text_window::TextWindow;
 use std::io;
 use termion::cursor;
 use tui::backend::Backend;
 use tui::layout::{Constraint, Direction, Layout, Rect};
-use tui::style::{Color, Style};
-use tui::text::Text;
+use tui::text::{Span, Spans, Text};
 use tui::widgets::{Block, Paragraph};
 use tui::{Frame, Terminal};
 
 pub fn draw<B: Backend>(editor: &mut Editor, terminal: &mut Terminal<B>) -> Result<(), io::Error> {
     terminal.draw(|f| {
         let area = f.size();
+
+        // Multi-line messages, like :messages, grow the command line upwards
+        let commandline_height = match &editor.messages.current {
+            Some(message) => message.text.lines().count().max(1) as u16,
+            None => 1,
+        };
+        let commandline_height = commandline_height.min(area.height.saturating_sub(2).max(1));
+
         let chunks = Layout::default()
             .direction(Direction::Vertical)
-            .constraints(
-                [
-                    Constraint::Min(10),
-                    Constraint::Length(1),
-                    Constraint::Length(1),
-                ]
-                .as_ref(),
-            )
+            .constraints([Constraint::Min(2), Constraint::Length(commandline_height)].as_ref())
             .split(area);
 
-        // There's some bug with the first line, so skip it for now
-        draw_text(editor, chunks[0], f);
-        draw_statusline(editor, chunks[1], f);
-        draw_commandline(editor, chunks[2], f);
+        let mut windows_area = chunks[0];
+        if editor.tabs.count() > 1 && windows_area.height > 2 {
+            draw_tabline(
+                editor,
+                Rect {
+                    height: 1,
+                    ..windows_area
+                },
+                f,
+            );
+            windows_area.y += 1;
+            windows_area.height -= 1;
+        }
+        if editor.cmdline_window.is_some() {
+            windows_area = draw_cmdline_window_split(editor, windows_area, f);
+        }
+        draw_windows(editor, windows_area, f);
+        draw_commandline(editor, chunks[1], f);
     })
 }
 
+/**
+ * One label per tab page, with the current one highlighted
+ */
+fn draw_tabline<B: Backend>(editor: &Editor, area: Rect, frame: &mut Frame<B>) {
+    let style = editor.colorscheme.style("TabLine");
+    let current_style = editor.colorscheme.style("TabLineSel");
+
+    let spans = editor
+        .tab_labels()
+        .into_iter()
+        .enumerate()
+        .map(|(i, label)| {
+            let span_style = if i == editor.tabs.current {
+                current_style
+            } else {
+                style
+            };
+            Span::styled(format!(" {} {} ", i + 1, label), span_style)
+        })
+        .collect::<Vec<Span>>();
+    let paragraph = Paragraph::new(Spans::from(spans)).style(style);
+    frame.render_widget(paragraph, area);
+}
+
+/**
+ * Draw the command-line window at the bottom and return the area left for
+ * the other windows
+ */
+fn draw_cmdline_window_split<B: Backend>(
+    editor: &mut Editor,
+    area: Rect,
+    frame: &mut Frame<B>,
+) -> Rect {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Min(2),
+                Constraint::Length(cmdline_window::HEIGHT),
+                Constraint::Length(1),
+            ]
+            .as_ref(),
+        )
+        .split(area);
+
+    draw_text(editor, chunks[1], frame);
+
+    let window = editor.cmdline_window.as_ref().unwrap();
+    let status = format!("[Command Line] {}", window.kind.prompt());
+    let paragraph = Paragraph::new(status.as_str()).style(editor.colorscheme.style("StatusLine"));
+    frame.render_widget(paragraph, chunks[2]);
+
+    chunks[0]
+}
+
+/**
+ * Draw every window in the layout, each with its own status line, and the
+ * separators between side by side windows
+ */
+fn draw_windows<B: Backend>(editor: &mut Editor, area: Rect, frame: &mut Frame<B>) {
+    let separator_style = editor.colorscheme.style("VertSplit");
+    for (id, window_area) in editor.windows.layout.areas(area) {
+        let text_area = Rect {
+            height: window_area.height.saturating_sub(1),
+            ..window_area
+        };
+        let status_area = Rect {
+            y: window_area.bottom().saturating_sub(1),
+            height: window_area.height.min(1),
+            ..window_area
+        };
+
+        if id == editor.windows.current && editor.cmdline_window.is_none() {
+            draw_text(editor, text_area, frame);
+            draw_statusline(editor, status_area, frame);
+        } else {
+            draw_inactive_window(editor, id, text_area, frame);
+            draw_inactive_statusline(editor, id, status_area, frame);
+        }
+
+        if window_area.right() < area.right() {
+            let separator = vec![Spans::from("\u{2502}"); window_area.height as usize];
+            let paragraph = Paragraph::new(separator).style(separator_style);
+            let separator_area =
+                Rect::new(window_area.right(), window_area.y, 1, window_area.height);
+            frame.render_widget(paragraph, separator_area);
+        }
+    }
+}
+
+/**
+ * A window without the cursor. While the command-line window is open the
+ * current window is one of these too, showing the buffer it swapped out.
+ */
+fn draw_inactive_window<B: Backend>(
+    editor: &mut Editor,
+    id: usize,
+    area: Rect,
+    frame: &mut Frame<B>,
+) {
+    let (buffer, cursor, state) = if id == editor.windows.current {
+        let window = editor.cmdline_window.as_mut().unwrap();
+        let cursor = window.buffer.cursor();
+        (&mut window.buffer, cursor, &mut window.window_state)
+    } else {
+        let window = match editor.windows.get_mut(id) {
+            Some(window) => window,
+            None => return,
+        };
+        let buffer = match (
+            editor.buffers.get_mut(window.buffer),
+            &mut editor.cmdline_window,
+        ) {
+            (Some(entry), _) => &mut entry.buffer,
+            (None, Some(cmdline_window)) => &mut cmdline_window.buffer,
+            (None, None) => &mut editor.text_buffer,
+        };
+        let last = buffer.text_buffer.line_count();
+        let cursor = Position::new(window.cursor.line.min(last), window.cursor.col);
+        (buffer, cursor, &mut window.state)
+    };
+
+    // Enough to cover the lines shown once the window scrolls to the cursor
+    buffer.update_syntax(state.offset.max(cursor.line) + area.height as usize);
+    let colorscheme = &editor.colorscheme;
+    let mut paragraph = TextWindow::new(buffer, colorscheme)
+        .cursor(cursor)
+        .scrolloff(editor.options.scrolloff);
+    if let Some(search) = &editor.search {
+        if editor.options.hlsearch && editor.search_highlight {
+            let search_style = colorscheme.style("Search");
+            paragraph = paragraph.search(&search.regex, search_style);
+        }
+    }
+    frame.render_stateful_widget(paragraph, area, state);
+}
+
 fn draw_text<B: Backend>(editor: &mut Editor, area: Rect, frame: &mut Frame<B>) {
-    let paragraph = TextWindow::new(&editor.text_buffer)
-        .style(Style::default().fg(Color::White).bg(Color::Black));
+    let offset = editor.text_window_state.offset;
+    let cursor_line = editor.text_buffer.cursor().line;
+    editor
+        .text_buffer
+        .update_syntax(offset.max(cursor_line) + area.height as usize);
+
+    let colorscheme = &editor.colorscheme;
+    let mut paragraph =
+        TextWindow::new(&editor.text_buffer, colorscheme).scrolloff(editor.options.scrolloff);
+
+    // While typing a search, preview its matches instead of the last search
+    let search_style = colorscheme.style("Search");
+    match editor.state_stack.last() {
+        Some(State::Search(SearchState {
+            preview: Some((search, found)),
+            ..
+        })) => {
+            paragraph = paragraph
+                .search(&search.regex, search_style)
+                .highlight(*found, colorscheme.style("IncSearch"));
+        }
+        state => {
+            if let Some(search) = &editor.search {
+                if editor.options.hlsearch && editor.search_highlight {
+                    paragraph = paragraph.search(&search.regex, search_style);
+                }
+            }
+
+            // Confirming a substitute highlights the match in question
+            if let Some(State::Substitute(s)) = state {
+                paragraph = paragraph.highlight(s.current(), colorscheme.style("IncSearch"));
+            }
+
+            if let Some(State::Visual(visual)) = state {
+                let buffer = &editor.text_buffer;
+                let (start, end) = visual.selection(buffer.cursor());
+                for line in start.line..=end.line {
+                    let from = if line == start.line { start.col } else { 0 };
+                    let to = if line == end.line {
+                        // Cover the whole of the last char, however many bytes
+                        let text = buffer.text_buffer.line(line);
+                        let last = text.get(end.col..).and_then(|rest| rest.chars().next());
+                        end.col + last.map_or(1, char::len_utf8)
+                    } else {
+                        buffer.text_buffer.line_length(line) + 1
+                    };
+                    paragraph = paragraph.highlight(
+                        Range::new(Position::new(line, from), to.saturating_sub(from)),
+                        colorscheme.style("Visual"),
+                    );
+                }
+            }
+        }
+    }
     frame.render_stateful_widget(paragraph, area, &mut editor.text_window_state);
 
     // Draw the cursor in the text
     let state = editor.state();
     match state {
-        State::Normal(_) | State::Insert(_) | State::DeleteOperator(_) => (),
+        State::Normal(_) | State::Insert(_) | State::DeleteOperator(_) | State::Visual(_) => (),
         _ => return,
     }
 
-    if let State::Normal(_) = state {
+    if let State::Normal(_) | State::Visual(_) = state {
         print!("{}", cursor::SteadyBlock);
     }
     if let State::Insert(_) = state {
@@ -55,45 +263,182 @@ fn draw_text<B: Backend>(editor: &mut Editor, area: Rect, frame: &mut Frame<B>)
         print!("{}", cursor::SteadyUnderline);
     }
 
-    let cursor = editor.text_buffer.cursor();
-    let offset = editor.text_window_state.offset;
-    frame.set_cursor(
-        area.x + cursor.col as u16 % area.width,
-        area.y + cursor.line as u16 + cursor.col as u16 / area.width - offset as u16,
-    )
+    let (x, y) = editor.text_window_state.cursor;
+    if x < area.width && y < area.height {
+        frame.set_cursor(area.x + x, area.y + y);
+    }
 }
 
 fn draw_statusline<B: Backend>(editor: &Editor, area: Rect, frame: &mut Frame<B>) {
+    if let State::Command(CommandState {
+        completion: Some(completion),
+        ..
+    }) = editor.state()
+    {
+        draw_wildmenu(editor, completion, area, frame);
+        return;
+    }
+
     let status = format!(
         "{} | {}",
         editor.mode(),
-        editor.filename.as_ref().unwrap_or(&"No File".to_string()),
+        file_status(&editor.filename, &editor.file_info, editor.is_modified())
     );
     let text = Text::from(status.as_str());
-    let paragraph = Paragraph::new(text).style(Style::default().bg(Color::Gray).fg(Color::Black));
+    let paragraph = Paragraph::new(text).style(editor.colorscheme.style("StatusLine"));
     frame.render_widget(paragraph, area);
 }
 
-fn draw_commandline<B: Backend>(editor: &Editor, area: Rect, frame: &mut Frame<B>) {
-    let style = Style::default().bg(Color::Black).fg(Color::White);
-    let state = match editor.state() {
-        State::Command(s) => s,
-        _ => {
+fn draw_inactive_statusline<B: Backend>(
+    editor: &Editor,
+    id: usize,
+    area: Rect,
+    frame: &mut Frame<B>,
+) {
+    let buffer = editor.windows.get(id).map(|window| window.buffer);
+    let status = match (
+        buffer.and_then(|id| editor.buffers.get(id)),
+        &editor.cmdline_window,
+    ) {
+        (Some(entry), _) => file_status(&entry.filename, &entry.file_info, entry.is_modified()),
+        (None, Some(window)) => {
+            let modified = window.buffer.text_buffer.change_tick != window.file_info.saved_tick
+                || window.file_info.format != window.file_info.saved_format;
+            file_status(&window.filename, &window.file_info, modified)
+        }
+        (None, None) => file_status(&editor.filename, &editor.file_info, editor.is_modified()),
+    };
+    let paragraph = Paragraph::new(status.as_str()).style(editor.colorscheme.style("StatusLineNC"));
+    frame.render_widget(paragraph, area);
+}
+
+fn file_status(filename: &Option<String>, file_info: &FileInfo, modified: bool) -> String {
+    format!(
+        "{}{}{}",
+        filename.as_deref().unwrap_or("No File"),
+        if file_info.readonly { " [RO]" } else { "" },
+        if modified { " [+]" } else { "" },
+    )
+}
+
+/**
+ * Tab completion candidates, scrolled so the selected one is visible
+ */
+fn draw_wildmenu<B: Backend>(
+    editor: &Editor,
+    completion: &Completion,
+    area: Rect,
+    frame: &mut Frame<B>,
+) {
+    let style = editor.colorscheme.style("StatusLine");
+    let selected_style = editor.colorscheme.style("WildMenu");
+
+    let width = area.width as usize;
+    let mut first = 0;
+    if let Some(selected) = completion.selected {
+        let mut used = 0;
+        for (i, candidate) in completion.candidates[..=selected].iter().enumerate().rev() {
+            used += candidate.len() + 2;
+            if used > width {
+                first = i + 1;
+                break;
+            }
+        }
+    }
+
+    let mut spans = vec![];
+    if first > 0 {
+        spans.push(Span::styled("< ", style));
+    }
+    for (i, candidate) in completion.candidates.iter().enumerate().skip(first) {
+        let span_style = if completion.selected == Some(i) {
+            selected_style
+        } else {
+            style
+        };
+        spans.push(Span::styled(candidate.as_str(), span_style));
+        spans.push(Span::styled("  ", style));
+    }
+
+    let paragraph = Paragraph::new(Spans::from(spans)).style(style);
+    frame.render_widget(paragraph, area);
+}
+
+fn draw_message<B: Backend>(editor: &Editor, area: Rect, frame: &mut Frame<B>) {
+    let style = editor.colorscheme.style("Normal");
+    let message = match &editor.messages.current {
+        Some(message) => message,
+        None => {
             let block = Block::default().style(style);
             frame.render_widget(block, area);
             return;
         }
     };
 
-    let text = format!(":{}", state.buffer.text_buffer.to_string());
+    let text_style = match message.kind {
+        MessageKind::Info => style,
+        MessageKind::Error => editor.colorscheme.style("ErrorMsg"),
+    };
+    let lines = message
+        .text
+        .lines()
+        .map(|line| Spans::from(Span::styled(line, text_style)))
+        .collect::<Vec<Spans>>();
+    let text = Text::from(lines);
+    let paragraph = Paragraph::new(text).style(style);
+    frame.render_widget(paragraph, area);
+}
+
+fn draw_commandline<B: Backend>(editor: &Editor, area: Rect, frame: &mut Frame<B>) {
+    let style = editor.colorscheme.style("Normal");
+    let (prompt, buffer) = match editor.state() {
+        State::Command(s) => (':', &s.prompt.buffer),
+        State::Search(s) => (s.direction.prompt(), &s.prompt.buffer),
+        State::Substitute(s) => {
+            let text = format!("replace with {} (y/n/a/q/l)?", s.replacement());
+            let paragraph = Paragraph::new(text.as_str()).style(style);
+            frame.render_widget(paragraph, area);
+            return;
+        }
+        _ => {
+            draw_message(editor, area, frame);
+            return;
+        }
+    };
+
+    let text = format!("{}{}", prompt, buffer.text_buffer.to_string());
     let paragraph = Paragraph::new(text.as_str()).style(style);
     frame.render_widget(paragraph, area);
 
     // Handle cursor
-    let cursor = &state.buffer.cursor();
+    let cursor = &buffer.cursor();
     print!("{}", cursor::SteadyBlock);
     frame.set_cursor(
         area.x + cursor.col as u16 + 1,
         area.y, // Always one line
     )
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::buffer::Buffer;
+    use crate::text::PieceTableBuffer;
+    use termion::event::Key;
+    use tui::backend::TestBackend;
+
+    #[test]
+    fn test_visual_multibyte() {
+        let mut editor = Editor::new();
+        editor.text_buffer = Buffer::new(Box::new(PieceTableBuffer::new("éa\n".to_string())));
+        editor.execute_keys(&[Key::Char('v')], false);
+
+        let mut terminal = Terminal::new(TestBackend::new(20, 5)).unwrap();
+        draw(&mut editor, &mut terminal).unwrap();
+        let screen = terminal.backend().buffer();
+        let visual = editor.colorscheme.style("Visual");
+        assert_eq!(screen.get(0, 0).symbol, "é");
+        assert_eq!(Some(screen.get(0, 0).bg), visual.bg);
+        assert_ne!(Some(screen.get(1, 0).bg), visual.bg);
+    }
+}
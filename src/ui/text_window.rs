@@ -1,9 +1,14 @@
-use crate::buffer;
-use crate::text::{PieceTableBuffer, TextBuffer};
+use crate::buffer::{self, next_col, Position};
+use crate::colorscheme::Colorscheme;
+use crate::options::WindowOptions;
+use crate::search::line_matches;
+use crate::text::{PieceTableBuffer, Range, TextBuffer};
+use regex::Regex;
 use tui::{
     buffer::Buffer,
     layout::Rect,
     style::Style,
+    text::{Span, Spans, Text},
     widgets::{Paragraph, StatefulWidget, Widget},
 };
 
@@ -13,26 +18,104 @@ pub struct TextCursor {
     pub style: &'static str,
 }
 
+/**
+ * A window's scroll position and options, kept between draws
+ */
+#[derive(Debug, Clone)]
 pub struct TextWindowState {
     pub offset: usize,
+    // The first screen column shown, when lines don't wrap
+    pub leftcol: usize,
+    // Where the cursor was last drawn, relative to the window
+    pub cursor: (u16, u16),
+    pub options: WindowOptions,
+
+    // Where the window was last drawn, its line number width, and the
+    // line and first screen column shown on each row
+    pub area: Rect,
+    pub gutter: usize,
+    pub rows: Vec<(usize, usize)>,
 }
 
 impl TextWindowState {
     pub fn new() -> Self {
-        TextWindowState { offset: 0 }
+        TextWindowState {
+            offset: 0,
+            leftcol: 0,
+            cursor: (0, 0),
+            options: WindowOptions::new(),
+            area: Rect::default(),
+            gutter: 0,
+            rows: vec![],
+        }
+    }
+
+    /**
+     * Scroll as little as possible to show the cursor line, with up to
+     * `scrolloff` lines around it. `rows` is how many screen rows a line
+     * takes.
+     */
+    pub fn scroll_to_cursor(
+        &mut self,
+        cursor_line: usize,
+        scrolloff: usize,
+        height: usize,
+        line_count: usize,
+        rows: impl Fn(usize) -> usize,
+    ) {
+        let scrolloff = scrolloff.min(height.saturating_sub(1) / 2);
+        let bottom = (cursor_line + scrolloff).min(line_count.saturating_sub(1));
+        let top = cursor_line.saturating_sub(scrolloff);
+        self.offset = self
+            .offset
+            .min(top)
+            .max((bottom + 1).saturating_sub(height));
+        let mut used = (self.offset..=bottom).map(&rows).sum::<usize>();
+        while self.offset < top && used > height {
+            used -= rows(self.offset);
+            self.offset += 1;
+        }
+    }
+
+    /**
+     * The line and screen column drawn at a point relative to the window.
+     * Points below the text are on its last line.
+     */
+    pub fn position_at(&self, x: u16, y: u16) -> Option<(usize, usize)> {
+        let &(line, col) = self.rows.get(y as usize).or_else(|| self.rows.last())?;
+        Some((line, col + (x as usize).saturating_sub(self.gutter)))
+    }
+
+    /**
+     * The width text is drawn in, after the line numbers
+     */
+    pub fn text_width(&self) -> usize {
+        (self.area.width as usize)
+            .saturating_sub(self.gutter)
+            .max(1)
     }
 }
 
 pub struct TextWindow<'a> {
     style: Style,
     buffer: &'a buffer::Buffer<PieceTableBuffer>,
+    colorscheme: &'a Colorscheme,
+    cursor: Position,
+    scrolloff: usize,
+    search: Option<(&'a Regex, Style)>,
+    highlights: Vec<(Range, Style)>,
 }
 
 impl<'a> TextWindow<'a> {
-    pub fn new(buffer: &'a buffer::Buffer<PieceTableBuffer>) -> Self {
+    pub fn new(buffer: &'a buffer::Buffer<PieceTableBuffer>, colorscheme: &'a Colorscheme) -> Self {
         TextWindow {
-            style: Style::default(),
+            style: colorscheme.style("Normal"),
             buffer,
+            colorscheme,
+            cursor: buffer.cursor(),
+            scrolloff: 0,
+            search: None,
+            highlights: vec![],
         }
     }
 
@@ -40,34 +123,320 @@ impl<'a> TextWindow<'a> {
         self.style = style;
         self
     }
+
+    /**
+     * Scroll to this cursor instead of the buffer's, for windows that
+     * aren't current
+     */
+    pub fn cursor(mut self, cursor: Position) -> Self {
+        self.cursor = cursor;
+        self
+    }
+
+    /**
+     * Keep this many lines visible above and below the cursor
+     */
+    pub fn scrolloff(mut self, scrolloff: usize) -> Self {
+        self.scrolloff = scrolloff;
+        self
+    }
+
+    /**
+     * Highlight every match of the pattern in the visible lines
+     */
+    pub fn search(mut self, regex: &'a Regex, style: Style) -> Self {
+        self.search = Some((regex, style));
+        self
+    }
+
+    /**
+     * Highlight a range within a single line. Drawn over search matches.
+     */
+    pub fn highlight(mut self, range: Range, style: Style) -> Self {
+        self.highlights.push((range, style));
+        self
+    }
+
+    fn highlight_text(&self, text: Text, offset: usize) -> Text<'static> {
+        let lines = text
+            .lines
+            .into_iter()
+            .enumerate()
+            .map(|(i, spans)| {
+                let line = spans
+                    .0
+                    .iter()
+                    .map(|span| span.content.as_ref())
+                    .collect::<String>();
+                let lineno = offset + i;
+
+                let mut ranges = vec![];
+                if let Some(syntax) = &self.buffer.syntax {
+                    ranges.extend(syntax.tokens(lineno).iter().map(|token| {
+                        let style = self.colorscheme.style(token.group.name());
+                        (token.start, token.end, style)
+                    }));
+                }
+                if let Some((regex, style)) = self.search {
+                    ranges.extend(
+                        line_matches(regex, line.as_str())
+                            .into_iter()
+                            .map(|(start, end)| (start, end, style)),
+                    );
+                }
+                ranges.extend(
+                    self.highlights
+                        .iter()
+                        .filter(|(range, _)| range.start.line == lineno)
+                        .map(|(range, style)| {
+                            (range.start.col, range.start.col + range.length, *style)
+                        }),
+                );
+
+                highlight_line(line, ranges)
+            })
+            .collect::<Vec<Spans>>();
+        Text::from(lines)
+    }
+}
+
+/**
+ * Split a line into spans styled by the byte ranges. Later ranges win
+ * where they overlap.
+ */
+fn highlight_line(line: String, ranges: Vec<(usize, usize, Style)>) -> Spans<'static> {
+    if ranges.is_empty() {
+        return Spans::from(line);
+    }
+
+    let mut styles = vec![Style::default(); line.len()];
+    for (start, end, style) in ranges {
+        let end = end.min(line.len());
+        for s in styles[start.min(end)..end].iter_mut() {
+            *s = style;
+        }
+    }
+
+    let mut spans = vec![];
+    let mut start = 0;
+    for idx in 1..=line.len() {
+        if idx == line.len() || styles[idx] != styles[start] {
+            spans.push(Span::styled(line[start..idx].to_string(), styles[start]));
+            start = idx;
+        }
+    }
+    Spans::from(spans)
 }
+
+/**
+ * The characters of a line and their styles, one per screen column, with
+ * tabs expanded to spaces
+ */
+fn screen_cells(spans: Spans, tabstop: usize) -> Vec<(char, Style)> {
+    let mut cells = vec![];
+    for span in spans.0 {
+        for c in span.content.chars() {
+            let end = next_col(cells.len(), c, tabstop);
+            let c = if c == '\t' { ' ' } else { c };
+            cells.resize(end, (c, span.style));
+        }
+    }
+    cells
+}
+
 /**
- * This widget is pretty similar to the built in Paragraph, but handles scrolling based
- * on cursor position.
+ * The screen rows a line takes in a window of the given width
+ */
+pub fn screen_rows(
+    buffer: &buffer::Buffer<PieceTableBuffer>,
+    line: usize,
+    width: usize,
+    wrap: bool,
+) -> usize {
+    if !wrap {
+        return 1;
+    }
+    let tabstop = buffer.options.tabstop;
+    let cols = buffer
+        .text_buffer
+        .line(line)
+        .chars()
+        .fold(0, |col, c| next_col(col, c, tabstop));
+    cols.div_ceil(width).max(1)
+}
+
+/**
+ * Join runs of cells with the same style back into spans
+ */
+fn cell_spans(cells: &[(char, Style)]) -> Vec<Span<'static>> {
+    let mut spans: Vec<Span> = vec![];
+    for &(c, style) in cells {
+        match spans.last_mut() {
+            Some(span) if span.style == style => span.content.to_mut().push(c),
+            _ => spans.push(Span::styled(c.to_string(), style)),
+        }
+    }
+    spans
+}
+
+/**
+ * This widget is pretty similar to the built in Paragraph, but handles
+ * scrolling based on cursor position, and wraps lines or scrolls them
+ * sideways depending on the window's options.
  */
 impl<'a> StatefulWidget for TextWindow<'a> {
     type State = TextWindowState;
 
     fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
-        let cursor = self.buffer.cursor();
-
-        // Compute new scroll offset
-        let top = state.offset;
-        let bottom = top + area.height as usize;
-        state.offset = if cursor.line < top {
-            cursor.line
-        } else if cursor.line >= bottom {
-            cursor.line + 1 - area.height as usize
+        if area.width == 0 || area.height == 0 {
+            return;
+        }
+        let cursor = self.cursor;
+        let options = state.options.clone();
+        let tabstop = self.buffer.options.tabstop;
+        let text_buffer = &self.buffer.text_buffer;
+        let line_count = text_buffer.line_count();
+        let height = area.height as usize;
+
+        // Line numbers are right aligned, with a space before the text
+        let gutter = if options.number {
+            line_count.to_string().len().max(3) + 1
         } else {
-            state.offset
+            0
+        };
+        let width = (area.width as usize).saturating_sub(gutter).max(1);
+
+        // The screen rows each line takes, leaving room for the cursor past
+        // the end of the line
+        let cursor_col = self.buffer.display_col(cursor);
+        let rows = |line: usize| {
+            let rows = screen_rows(self.buffer, line, width, options.wrap);
+            if options.wrap && line == cursor.line {
+                rows.max(cursor_col / width + 1)
+            } else {
+                rows
+            }
         };
 
-        let paragraph = Paragraph::new(
-            self.buffer
-                .text_buffer
-                .to_text(state.offset, area.height as usize),
-        )
-        .style(self.style);
+        state.scroll_to_cursor(cursor.line, self.scrolloff, height, line_count, rows);
+
+        if options.wrap {
+            state.leftcol = 0;
+        } else if cursor_col < state.leftcol {
+            state.leftcol = cursor_col;
+        } else if cursor_col >= state.leftcol + width {
+            state.leftcol = cursor_col + 1 - width;
+        }
+
+        let text = text_buffer.to_text(state.offset, height);
+        let text = self.highlight_text(text, state.offset);
+        let number_style = self.colorscheme.style("LineNr");
+        let mut screen = vec![];
+        state.area = area;
+        state.gutter = gutter;
+        state.rows.clear();
+        for (i, spans) in text.lines.into_iter().enumerate() {
+            let line = state.offset + i;
+            let cells = screen_cells(spans, tabstop);
+            let line_rows = if options.wrap {
+                cells.chunks(width).collect::<Vec<_>>()
+            } else {
+                let start = state.leftcol.min(cells.len());
+                let end = (state.leftcol + width).min(cells.len());
+                vec![&cells[start..end]]
+            };
+
+            // Every line gets at least one row, and the cursor line enough
+            // for a cursor just past its end
+            let mut min_rows = 1;
+            if line == cursor.line {
+                let (x, y) = if options.wrap {
+                    (cursor_col % width, cursor_col / width)
+                } else {
+                    (cursor_col - state.leftcol, 0)
+                };
+                state.cursor = ((gutter + x) as u16, (screen.len() + y) as u16);
+                min_rows = y + 1;
+            }
+
+            let empty: &[(char, Style)] = &[];
+            let padding = std::iter::repeat(empty);
+            for (row, cells) in line_rows.into_iter().chain(padding).enumerate() {
+                if row >= min_rows && cells.is_empty() {
+                    break;
+                }
+                let mut spans = vec![];
+                if gutter > 0 {
+                    let number = if row == 0 {
+                        format!("{:>width$} ", line + 1, width = gutter - 1)
+                    } else {
+                        " ".repeat(gutter)
+                    };
+                    spans.push(Span::styled(number, number_style));
+                }
+                spans.extend(cell_spans(cells));
+                screen.push(Spans::from(spans));
+
+                let start = if options.wrap {
+                    row * width
+                } else {
+                    state.leftcol
+                };
+                state.rows.push((line, start));
+            }
+            if screen.len() >= height {
+                break;
+            }
+        }
+        state.rows.truncate(height);
+
+        let paragraph = Paragraph::new(Text::from(screen)).style(self.style);
         paragraph.render(area, buf);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn render(text: &str, cursor: Position, state: &mut TextWindowState) -> Vec<String> {
+        let mut buffer = buffer::Buffer::new(Box::new(PieceTableBuffer::new(text.to_string())));
+        buffer.options.tabstop = 4;
+        buffer.move_cursor(cursor);
+        let colorscheme = Colorscheme::new();
+        let area = Rect::new(0, 0, 10, 3);
+        let mut screen = Buffer::empty(area);
+        TextWindow::new(&buffer, &colorscheme)
+            .scrolloff(1)
+            .render(area, &mut screen, state);
+        (0..area.height)
+            .map(|y| {
+                (0..area.width)
+                    .map(|x| screen.get(x, y).symbol.clone())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_wrap_and_number() {
+        let text = "a\tb\nlong line\nc\nd\n";
+        let mut state = TextWindowState::new();
+        state.options.number = true;
+        let lines = render(text, Position::new(1, 7), &mut state);
+        assert_eq!(lines, vec!["  1 a   b ", "  2 long l", "    ine   "]);
+        assert_eq!(state.cursor, (5, 2));
+
+        // Scrolling keeps a line of context above the cursor
+        let lines = render(text, Position::new(2, 0), &mut state);
+        assert_eq!(state.offset, 1);
+        assert_eq!(lines, vec!["  2 long l", "    ine   ", "  3 c     "]);
+
+        // Without wrapping, lines scroll sideways to the cursor
+        state.options.wrap = false;
+        state.offset = 0;
+        let lines = render(text, Position::new(1, 8), &mut state);
+        assert_eq!(lines[1], "  2 g line");
+        assert_eq!(state.cursor, (9, 1));
+    }
+}
@@ -0,0 +1,470 @@
+use crate::buffer::Position;
+use crate::editor::Editor;
+use crate::ex::{last_line, ExError};
+use crate::text::{Anchor, TextBuffer};
+use crate::ui::text_window::TextWindowState;
+use tui::layout::Rect;
+
+const WEIGHT: u32 = 1024;
+
+/**
+ * Horizontal splits stack windows on top of each other, vertical splits
+ * put them side by side
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SplitDirection {
+    Horizontal,
+    Vertical,
+}
+
+/**
+ * The window tree. Children of a split share its space by weight.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum Layout {
+    Window(usize),
+    Split(SplitDirection, Vec<(Layout, u32)>),
+}
+
+impl Layout {
+    pub fn window_ids(&self) -> Vec<usize> {
+        match self {
+            Layout::Window(id) => vec![*id],
+            Layout::Split(_, children) => children
+                .iter()
+                .flat_map(|(child, _)| child.window_ids())
+                .collect(),
+        }
+    }
+
+    /**
+     * Put a new window before the existing one, halving its space
+     */
+    fn split(&mut self, id: usize, new_id: usize, direction: SplitDirection) -> bool {
+        match self {
+            Layout::Window(window) if *window == id => {
+                let children = vec![
+                    (Layout::Window(new_id), WEIGHT),
+                    (Layout::Window(id), WEIGHT),
+                ];
+                *self = Layout::Split(direction, children);
+                true
+            }
+            Layout::Window(_) => false,
+            Layout::Split(split_direction, children) => {
+                let idx = match children
+                    .iter()
+                    .position(|(child, _)| *child == Layout::Window(id))
+                {
+                    Some(idx) if *split_direction == direction => idx,
+                    _ => {
+                        return children
+                            .iter_mut()
+                            .any(|(child, _)| child.split(id, new_id, direction))
+                    }
+                };
+                let weight = children[idx].1;
+                children[idx].1 = weight - weight / 2;
+                children.insert(idx, (Layout::Window(new_id), weight / 2));
+                true
+            }
+        }
+    }
+
+    /**
+     * Remove a window, giving its space to a neighbour and collapsing
+     * splits left with a single child
+     */
+    fn remove(&mut self, id: usize) {
+        if let Layout::Split(_, children) = self {
+            if let Some(idx) = children
+                .iter()
+                .position(|(child, _)| *child == Layout::Window(id))
+            {
+                let (_, weight) = children.remove(idx);
+                let neighbour = idx.min(children.len() - 1);
+                children[neighbour].1 += weight;
+            } else {
+                children.iter_mut().for_each(|(child, _)| child.remove(id));
+            }
+
+            if children.len() == 1 {
+                *self = children.remove(0).0;
+            }
+        }
+    }
+
+    fn equalize(&mut self) {
+        if let Layout::Split(_, children) = self {
+            for (child, weight) in children.iter_mut() {
+                *weight = WEIGHT;
+                child.equalize();
+            }
+        }
+    }
+
+    /**
+     * The area of each window, including its status line. Side by side
+     * windows leave a column between them for a separator.
+     */
+    pub fn areas(&self, area: Rect) -> Vec<(usize, Rect)> {
+        let (direction, children) = match self {
+            Layout::Window(id) => return vec![(*id, area)],
+            Layout::Split(direction, children) => (direction, children),
+        };
+
+        let (total, gap) = match direction {
+            SplitDirection::Horizontal => (area.height, 0),
+            SplitDirection::Vertical => (area.width, 1),
+        };
+        let gaps = gap * (children.len() as u16 - 1);
+        let available = total.saturating_sub(gaps) as u32;
+        let weights = children
+            .iter()
+            .map(|(_, weight)| weight)
+            .sum::<u32>()
+            .max(1);
+
+        let mut areas = vec![];
+        let mut start = 0;
+        let mut used_weight = 0;
+        for (i, (child, weight)) in children.iter().enumerate() {
+            // Round the running total so the sizes always add up
+            used_weight += weight;
+            let end = (available * used_weight / weights) as u16;
+            let size = end - start.min(end);
+            let child_area = match direction {
+                SplitDirection::Horizontal => Rect::new(area.x, area.y + start, area.width, size),
+                SplitDirection::Vertical => {
+                    let x = area.x + start + gap * i as u16;
+                    Rect::new(x, area.y, size, area.height)
+                }
+            };
+            start = end;
+            areas.extend(child.areas(child_area));
+        }
+        areas
+    }
+}
+
+/**
+ * A window other than the current one. The current window's cursor and
+ * scroll position are the editor's own.
+ */
+pub struct Window {
+    pub id: usize,
+    pub buffer: usize,
+    pub cursor: Position,
+    // The cursor line, followed through edits made from other windows
+    pub anchor: Option<Anchor>,
+    pub state: TextWindowState,
+}
+
+pub struct Windows {
+    pub current: usize,
+    pub layout: Layout,
+    hidden: Vec<Window>,
+    next_id: usize,
+}
+
+impl Windows {
+    pub fn new() -> Self {
+        Windows {
+            current: 1,
+            layout: Layout::Window(1),
+            hidden: vec![],
+            next_id: 2,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.hidden.len() + 1
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Window> {
+        self.hidden.iter().find(|window| window.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut Window> {
+        self.hidden.iter_mut().find(|window| window.id == id)
+    }
+
+    fn take(&mut self, id: usize) -> Option<Window> {
+        let idx = self.hidden.iter().position(|window| window.id == id)?;
+        Some(self.hidden.remove(idx))
+    }
+}
+
+impl Default for Windows {
+    fn default() -> Self {
+        Windows::new()
+    }
+}
+
+impl Editor {
+    /**
+     * Split the current window. The new window shows the same buffer,
+     * takes the global window options and becomes current.
+     */
+    pub fn split_window(&mut self, direction: SplitDirection) {
+        let id = self.windows.next_id;
+        self.windows.next_id += 1;
+
+        let current = self.windows.current;
+        self.windows.layout.split(current, id, direction);
+        self.windows.hidden.push(self.current_window(current));
+        self.windows.current = id;
+        self.text_window_state.options = self.options.window.clone();
+    }
+
+    pub fn switch_window(&mut self, id: usize) {
+        let window = match self.windows.take(id) {
+            Some(window) => window,
+            None => return,
+        };
+        let current = self.windows.current;
+        self.windows.hidden.push(self.current_window(current));
+        self.restore_window(window);
+    }
+
+    /**
+     * Make a window current, showing its buffer where it left off
+     */
+    pub fn restore_window(&mut self, window: Window) {
+        self.windows.current = window.id;
+
+        // The buffer may be gone if it was deleted while out of view. Moving
+        // between windows isn't editing another file, so the alternate
+        // buffer stays as it was.
+        let alternate = self.buffers.alternate;
+        let _ = self.switch_buffer(window.buffer);
+        self.buffers.alternate = alternate;
+
+        // Other windows may have edited the buffer since, or removed the
+        // cursor line altogether
+        let text = self.text_buffer.text_buffer.as_ref();
+        let line = window
+            .anchor
+            .as_ref()
+            .and_then(|anchor| text.anchor_line(anchor))
+            .unwrap_or(window.cursor.line)
+            .min(last_line(text));
+        let line_text = text.line(line);
+        let mut col = window.cursor.col.min(line_text.len());
+        while !line_text.is_char_boundary(col) {
+            col -= 1;
+        }
+        self.text_buffer.move_cursor(Position::new(line, col));
+        self.text_window_state = window.state;
+    }
+
+    /**
+     * Close the current window. Closing the last window of a tab page
+     * closes the tab page.
+     */
+    pub fn close_window(&mut self) -> Result<(), ExError> {
+        let ids = self.windows.layout.window_ids();
+        let idx = ids
+            .iter()
+            .position(|&id| id == self.windows.current)
+            .unwrap_or(0);
+        let next = match (ids.get(idx + 1), idx.checked_sub(1)) {
+            (Some(&next), _) => next,
+            (None, Some(prev)) => ids[prev],
+            (None, None) if self.tabs.count() > 1 => return self.close_tab(),
+            (None, None) => return Err(ExError::LastWindow),
+        };
+
+        let closing = self.windows.current;
+        self.switch_window(next);
+        self.windows.take(closing);
+        self.windows.layout.remove(closing);
+        Ok(())
+    }
+
+    /**
+     * Close every window but the current one
+     */
+    pub fn only_window(&mut self) {
+        self.windows.hidden.clear();
+        self.windows.layout = Layout::Window(self.windows.current);
+    }
+
+    pub fn equalize_windows(&mut self) {
+        self.windows.layout.equalize();
+    }
+
+    /**
+     * Move to the window next to the current one, in the direction of
+     * h, j, k or l
+     */
+    pub fn move_to_window(&mut self, direction: char) {
+        // Any size will do to find neighbours
+        let areas = self.windows.layout.areas(Rect::new(0, 0, 1000, 1000));
+        let current = match areas.iter().find(|(id, _)| *id == self.windows.current) {
+            Some((_, area)) => *area,
+            None => return,
+        };
+
+        let overlaps = |a: u16, a_len: u16, b: u16, b_len: u16| a < b + b_len && b < a + a_len;
+        let neighbour = areas
+            .iter()
+            .filter(|(_, area)| match direction {
+                'h' => {
+                    area.right() + 1 == current.x
+                        && overlaps(area.y, area.height, current.y, current.height)
+                }
+                'l' => {
+                    current.right() + 1 == area.x
+                        && overlaps(area.y, area.height, current.y, current.height)
+                }
+                'k' => {
+                    area.bottom() == current.y
+                        && overlaps(area.x, area.width, current.x, current.width)
+                }
+                'j' => {
+                    current.bottom() == area.y
+                        && overlaps(area.x, area.width, current.x, current.width)
+                }
+                _ => false,
+            })
+            // Prefer the one lined up with the cursor's side of the window
+            .min_by_key(|(_, area)| match direction {
+                'h' | 'l' => (area.y as i32 - current.y as i32).abs(),
+                _ => (area.x as i32 - current.x as i32).abs(),
+            })
+            .map(|(id, _)| *id);
+
+        if let Some(id) = neighbour {
+            self.switch_window(id);
+        }
+    }
+
+    /**
+     * The window drawn at a screen position, as of the last draw
+     */
+    pub fn window_at(&self, x: u16, y: u16) -> Option<usize> {
+        let contains =
+            |area: Rect| x >= area.x && x < area.right() && y >= area.y && y < area.bottom();
+        if contains(self.text_window_state.area) {
+            return Some(self.windows.current);
+        }
+        self.windows
+            .hidden
+            .iter()
+            .find(|window| contains(window.state.area))
+            .map(|window| window.id)
+    }
+
+    /**
+     * The current window's view, to keep while another window is current
+     */
+    pub fn current_window(&self, id: usize) -> Window {
+        let cursor = self.text_buffer.cursor();
+        Window {
+            id,
+            buffer: self.buffers.current,
+            cursor,
+            anchor: self.text_buffer.text_buffer.line_anchor(cursor.line),
+            state: self.text_window_state.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::buffer::Buffer;
+    use crate::ex;
+    use crate::text::PieceTableBuffer;
+    use crate::ui::draw::draw;
+    use tui::backend::TestBackend;
+    use tui::Terminal;
+
+    #[test]
+    fn test_layout_areas() {
+        let mut layout = Layout::Window(1);
+        layout.split(1, 2, SplitDirection::Vertical);
+        layout.split(1, 3, SplitDirection::Horizontal);
+        assert_eq!(layout.window_ids(), vec![2, 3, 1]);
+
+        let areas = layout.areas(Rect::new(0, 0, 81, 20));
+        assert_eq!(areas[0], (2, Rect::new(0, 0, 40, 20)));
+        assert_eq!(areas[1], (3, Rect::new(41, 0, 40, 10)));
+        assert_eq!(areas[2], (1, Rect::new(41, 10, 40, 10)));
+
+        layout.remove(3);
+        assert_eq!(layout.window_ids(), vec![2, 1]);
+        layout.remove(2);
+        assert_eq!(layout, Layout::Window(1));
+    }
+
+    #[test]
+    fn test_windows_share_buffer() {
+        let mut editor = Editor::new();
+        editor.text_buffer = Buffer::new(Box::new(PieceTableBuffer::new("a\nb\nc\n".to_string())));
+        editor.split_window(SplitDirection::Horizontal);
+        editor.text_buffer.move_cursor(Position::new(2, 0));
+        editor.text_buffer.insert('x');
+
+        // Each window keeps its own cursor, and both see the edit
+        editor.move_to_window('j');
+        assert_eq!(editor.windows.current, 1);
+        assert_eq!(editor.text_buffer.cursor(), Position::new(0, 0));
+        assert_eq!(editor.text_buffer.text_buffer.line(2), "xc");
+
+        editor.move_to_window('k');
+        assert_eq!(editor.text_buffer.cursor(), Position::new(2, 1));
+
+        // The other window's cursor follows its line through edits
+        editor.move_to_window('j');
+        ex::run("1d", &mut editor).unwrap();
+        editor.move_to_window('k');
+        assert_eq!(editor.text_buffer.cursor(), Position::new(1, 1));
+        editor.move_to_window('j');
+        ex::run("%d", &mut editor).unwrap();
+        editor.move_to_window('k');
+        assert_eq!(editor.text_buffer.cursor(), Position::new(0, 0));
+
+        editor.close_window().unwrap();
+        assert_eq!(editor.windows.count(), 1);
+        assert!(editor.close_window().is_err());
+    }
+
+    #[test]
+    fn test_windows_keep_alternate() {
+        let mut editor = Editor::new();
+        for name in &["one", "two"] {
+            let path = std::env::temp_dir().join(format!("vik-{}-{}", name, std::process::id()));
+            ex::run(&format!("e {}", path.to_str().unwrap()), &mut editor).unwrap();
+        }
+        editor.split_window(SplitDirection::Horizontal);
+        ex::run("b1", &mut editor).unwrap();
+        assert_eq!(editor.buffers.alternate, Some(2));
+
+        // Moving to a window on another buffer isn't editing it, so # stays
+        editor.move_to_window('j');
+        assert_eq!(editor.buffers.current, 2);
+        assert_eq!(editor.buffers.alternate, Some(2));
+        editor.move_to_window('k');
+        assert_eq!(editor.buffers.current, 1);
+        assert_eq!(editor.buffers.alternate, Some(2));
+    }
+
+    #[test]
+    fn test_resize() {
+        let mut editor = Editor::new();
+        let text = "line\n".repeat(50);
+        editor.text_buffer = Buffer::new(Box::new(PieceTableBuffer::new(text)));
+        editor.text_buffer.move_cursor(Position::new(30, 0));
+        editor.text_window_state.offset = 25;
+        editor.split_window(SplitDirection::Horizontal);
+        editor.text_buffer.move_cursor(Position::new(10, 0));
+
+        // Drawing at the new size scrolls each window to its cursor
+        let mut terminal = Terminal::new(TestBackend::new(80, 10)).unwrap();
+        draw(&mut editor, &mut terminal).unwrap();
+        assert_eq!(editor.text_window_state.offset, 10);
+        assert_eq!(editor.windows.get(1).unwrap().state.offset, 27);
+    }
+}